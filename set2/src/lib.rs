@@ -21,6 +21,15 @@ pub fn pkcs7_pad_block(data: &[u8], block_size: u32) -> Vec<u8> {
     padded
 }
 
+// Unlike pkcs7_pad_block, block-aligned input still gets a full block of
+// padding, so stripping it again is never ambiguous
+pub fn pkcs7_pad(data: &[u8], block_size: u32) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    let n_to_pad = block_size - ((data.len() as u32) % block_size);
+    padded.append(&mut vec![n_to_pad.try_into().unwrap(); n_to_pad.try_into().unwrap()]);
+    padded
+}
+
 pub fn pkcs7_pad_strip(data: &[u8]) -> Result<Vec<u8>, String> {
     let nbs = data.len();
     let padb = data[nbs-1];
@@ -275,6 +284,14 @@ mod tests {
         assert_eq!(pkcs7_pad_block(block, 20), expected);
         assert_eq!(pkcs7_pad_block(block, 16), block);
     }
+
+    #[test]
+    fn pkcs7_pad_always_pads() {
+        let block = b"YELLOW SUBMARINE";
+        assert_eq!(pkcs7_pad(block, 20), b"YELLOW SUBMARINE\x04\x04\x04\x04");
+        assert_eq!(pkcs7_pad(block, 16), [&block[..], &[16; 16]].concat());
+        assert_eq!(pkcs7_pad_strip(&pkcs7_pad(block, 16)).unwrap(), block);
+    }
         
     #[test]
     fn challenge10_cbc_inverse() {
@@ -63,7 +63,7 @@ pub fn ctr_apply(data: &[u8], nonce: u64, key: &[u8]) -> Vec<u8> {
  impl MersenneRNG {
     // Constant definitions for MT19937
     fn new() -> Self {
-        let lower = (18 << 31) - 18;
+        let lower = (18u32 << 31).wrapping_sub(18);
         MersenneRNG {
             index: 621+1,
             twister: vec![0;621],
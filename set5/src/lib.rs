@@ -0,0 +1,13 @@
+pub mod dh;
+pub mod mitm;
+pub mod numtheory;
+pub mod protocol;
+pub mod rsa;
+pub mod srp;
+pub mod ssrp;
+
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
@@ -0,0 +1,252 @@
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use set2::{cbc_decrypt, cbc_encrypt, generate_rand, pkcs7_pad, pkcs7_pad_strip};
+
+use crate::dh::{session_key, Group};
+use crate::protocol::{Direction, Endpoint, Interceptor};
+
+const BLOCK_SIZE: usize = 16;
+
+/*
+ * Diffie-Hellman echo protocol
+ *
+ * A -> B  p, g
+ * B -> A  ACK p, g   (the group Bob agreed to, which both sides then use)
+ * A -> B  A
+ * B -> A  B
+ * A -> B  AES-CBC(session_key(s), msg) + iv
+ * B -> A  AES-CBC(session_key(s), msg) + iv   (Bob echoes what he decrypted)
+ *
+ * Nothing in the protocol authenticates the group or the public keys, so
+ * whoever sits in the middle gets to pick values that make the shared secret
+ * predictable and then reads every message that goes past.
+ */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Params { p: BigUint, g: BigUint },
+    Ack { p: BigUint, g: BigUint },
+    PublicKey(BigUint),
+    Encrypted { ciphertext: Vec<u8>, iv: Vec<u8> },
+}
+
+pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Message {
+    let iv = generate_rand(BLOCK_SIZE);
+    Message::Encrypted { ciphertext: cbc_encrypt(&pkcs7_pad(plaintext, BLOCK_SIZE as u32), &iv, key), iv }
+}
+
+pub fn decrypt(key: &[u8], ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>, String> {
+    if ciphertext.is_empty() || ciphertext.len() % BLOCK_SIZE != 0 {
+        return Err(String::from("ciphertext is not a whole number of blocks"));
+    }
+    pkcs7_pad_strip(&cbc_decrypt(ciphertext, iv, key))
+}
+
+fn recv(endpoint: &Endpoint<Message>) -> Result<Message, String> {
+    endpoint.recv().map_err(|e| e.to_string())
+}
+
+fn unexpected(msg: Message) -> String {
+    format!("unexpected message: {:?}", msg)
+}
+
+// Alice negotiates the group, sends `plaintext` and returns whatever Bob echoed back
+pub fn alice(endpoint: Endpoint<Message>, group: Group, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    endpoint.send(Message::Params { p: group.p, g: group.g });
+    let group = match recv(&endpoint)? {
+        Message::Ack { p, g } => Group::new(p, g),
+        msg => return Err(unexpected(msg)),
+    };
+
+    let keys = group.generate_keypair();
+    endpoint.send(Message::PublicKey(keys.public.clone()));
+    let bob_public = match recv(&endpoint)? {
+        Message::PublicKey(public) => public,
+        msg => return Err(unexpected(msg)),
+    };
+
+    let key = session_key(&group.shared_secret(&keys.private, &bob_public));
+    endpoint.send(encrypt(&key, plaintext));
+    match recv(&endpoint)? {
+        Message::Encrypted { ciphertext, iv } => decrypt(&key, &ciphertext, &iv),
+        msg => Err(unexpected(msg)),
+    }
+}
+
+// Bob goes along with whatever group he's handed and returns the message he echoed
+pub fn bob(endpoint: Endpoint<Message>) -> Result<Vec<u8>, String> {
+    let group = match recv(&endpoint)? {
+        Message::Params { p, g } => Group::new(p, g),
+        msg => return Err(unexpected(msg)),
+    };
+    endpoint.send(Message::Ack { p: group.p.clone(), g: group.g.clone() });
+
+    let alice_public = match recv(&endpoint)? {
+        Message::PublicKey(public) => public,
+        msg => return Err(unexpected(msg)),
+    };
+    let keys = group.generate_keypair();
+    endpoint.send(Message::PublicKey(keys.public.clone()));
+
+    let key = session_key(&group.shared_secret(&keys.private, &alice_public));
+    let plaintext = match recv(&endpoint)? {
+        Message::Encrypted { ciphertext, iv } => decrypt(&key, &ciphertext, &iv)?,
+        msg => return Err(unexpected(msg)),
+    };
+    endpoint.send(encrypt(&key, &plaintext));
+    Ok(plaintext)
+}
+
+/*
+ * The attacks
+ *
+ * KeyFixing (challenge 34): swap both public keys for p, so both sides compute
+ * p^x mod p = 0 and the traffic can be read and forwarded untouched.
+ *
+ * The g attacks (challenge 35): tamper with the g Alice proposes. Bob acks it
+ * back and both sides go on to use it, so the public keys are predictable and
+ * so is the secret they agree on:
+ *   g = 1    =>  A = B = 1    =>  s = 1
+ *   g = p    =>  A = B = 0    =>  s = 0
+ *   g = p-1  =>  A, B = +-1   =>  s = (p-1)^ab, which is p-1 if a and b are
+ *                                 both odd and 1 otherwise
+ * No need to guess between 1 and p-1 either: A = p-1 exactly when a is odd,
+ * and the same for B, so the public keys on the wire settle it.
+ * Everything past the group goes through untouched, the MITM just reads it.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attack {
+    KeyFixing,
+    GIsOne,
+    GIsP,
+    GIsPMinusOne,
+}
+
+pub struct Mitm {
+    attack: Attack,
+    p: BigUint,
+    pub publics: Vec<BigUint>,
+    pub plaintexts: Vec<Vec<u8>>,
+}
+
+impl Mitm {
+    pub fn new(attack: Attack) -> Self {
+        Mitm { attack, p: BigUint::zero(), publics: vec![], plaintexts: vec![] }
+    }
+
+    // The secret both sides end up with, predicted from the g we handed them
+    pub fn secret(&self) -> BigUint {
+        let p_minus_one = &self.p - 1u32;
+        match self.attack {
+            Attack::KeyFixing | Attack::GIsP => BigUint::zero(),
+            Attack::GIsOne => BigUint::one(),
+            Attack::GIsPMinusOne if self.publics.iter().all(|public| public == &p_minus_one) => p_minus_one,
+            Attack::GIsPMinusOne => BigUint::one(),
+        }
+    }
+
+    fn read(&mut self, ciphertext: &[u8], iv: &[u8]) {
+        if let Ok(plaintext) = decrypt(&session_key(&self.secret()), ciphertext, iv) {
+            self.plaintexts.push(plaintext);
+        }
+    }
+}
+
+impl Interceptor<Message> for Mitm {
+    fn intercept(&mut self, direction: Direction, msg: Message) -> Option<Message> {
+        match (direction, msg) {
+            (Direction::AliceToBob, Message::Params { p, g }) => {
+                self.p = p.clone();
+                let g = match self.attack {
+                    Attack::KeyFixing => g,
+                    Attack::GIsOne => BigUint::one(),
+                    Attack::GIsP => p.clone(),
+                    Attack::GIsPMinusOne => &p - 1u32,
+                };
+                Some(Message::Params { p, g })
+            },
+            (_, Message::PublicKey(_)) if self.attack == Attack::KeyFixing => Some(Message::PublicKey(self.p.clone())),
+            (_, Message::PublicKey(public)) => {
+                self.publics.push(public.clone());
+                Some(Message::PublicKey(public))
+            },
+            (_, Message::Encrypted { ciphertext, iv }) => {
+                self.read(&ciphertext, &iv);
+                Some(Message::Encrypted { ciphertext, iv })
+            },
+            (_, msg) => Some(msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{run, Passthrough};
+
+    const SECRET: &[u8] = b"Play that funky music white boy";
+
+    fn echo<I: Interceptor<Message>>(interceptor: I) -> (Vec<u8>, Vec<u8>, I) {
+        let (alice, bob, interceptor) = run(
+            |ep| alice(ep, Group::nist(), SECRET),
+            bob,
+            interceptor,
+        );
+        (alice.unwrap(), bob.unwrap(), interceptor)
+    }
+
+    #[test]
+    fn challenge34_echo_protocol() {
+        let (alice, bob, _) = echo(Passthrough);
+        assert_eq!(alice, SECRET);
+        assert_eq!(bob, SECRET);
+    }
+
+    #[test]
+    fn challenge34_block_aligned_message() {
+        let (alice, _, _) = run(|ep| alice(ep, Group::nist(), b"YELLOW SUBMARINE"), bob, Passthrough);
+        assert_eq!(alice.unwrap(), b"YELLOW SUBMARINE");
+    }
+
+    #[test]
+    fn challenge34_key_fixing_attack() {
+        let (alice, bob, mitm) = echo(Mitm::new(Attack::KeyFixing));
+        assert_eq!(alice, SECRET);
+        assert_eq!(bob, SECRET);
+        assert_eq!(mitm.plaintexts, vec![SECRET.to_vec(), SECRET.to_vec()]);
+    }
+
+    #[test]
+    fn challenge35_malicious_g() {
+        let p = Group::nist().p;
+        for &(attack, ref publics) in &[
+            (Attack::GIsOne, vec![BigUint::one()]),
+            (Attack::GIsP, vec![BigUint::zero()]),
+            (Attack::GIsPMinusOne, vec![BigUint::one(), &p - 1u32]),
+        ] {
+            let (alice, bob, mitm) = echo(Mitm::new(attack));
+            assert_eq!(alice, SECRET);
+            assert_eq!(bob, SECRET);
+            // Both sides really did use the tampered g
+            assert_eq!(mitm.publics.len(), 2);
+            assert!(mitm.publics.iter().all(|public| publics.contains(public)));
+            assert_eq!(mitm.plaintexts, vec![SECRET.to_vec(), SECRET.to_vec()]);
+        }
+    }
+
+    // Over enough runs a and b are both odd at some point, and s is p-1
+    #[test]
+    fn challenge35_g_is_p_minus_one_both_secrets() {
+        let p = Group::nist().p;
+        let mut secrets = vec![];
+        for _ in 0..32 {
+            let (alice, _, mitm) = echo(Mitm::new(Attack::GIsPMinusOne));
+            assert_eq!(alice, SECRET);
+            assert_eq!(mitm.plaintexts, vec![SECRET.to_vec(), SECRET.to_vec()]);
+            secrets.push(mitm.secret());
+        }
+        assert!(secrets.contains(&BigUint::one()));
+        assert!(secrets.contains(&(p - 1u32)));
+    }
+}
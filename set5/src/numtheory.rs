@@ -0,0 +1,345 @@
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use rand::Rng;
+
+pub const MR_ROUNDS: usize = 32;
+const SIEVE_LIMIT: u32 = 1000;
+
+/*
+ * Number theory toolkit
+ *
+ * The building blocks the RSA, DSA and DH attacks keep reaching for. Anything
+ * random takes the Rng as an argument, so handing in a seeded StdRng makes
+ * key generation reproducible.
+ */
+
+// Right-to-left square and multiply
+pub fn modexp(base: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
+    if modulus.is_one() {
+        return BigUint::zero();
+    }
+    let mut result = BigUint::one();
+    let mut base = base % modulus;
+    for i in 0..exp.bits() {
+        if exp.bit(i) {
+            result = result * &base % modulus;
+        }
+        base = &base * &base % modulus;
+    }
+    result
+}
+
+// Returns (g, x, y) with ax + by = g = gcd(a, b), g non-negative
+pub fn egcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        let next_r = &old_r - &q * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &q * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &q * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+pub fn invmod(a: &BigUint, modulus: &BigUint) -> Option<BigUint> {
+    let m = BigInt::from(modulus.clone());
+    let (g, x, _) = egcd(&BigInt::from(a.clone()), &m);
+    if !g.is_one() {
+        return None;
+    }
+    x.mod_floor(&m).to_biguint()
+}
+
+// Solves x = r_i mod m_i for pairwise coprime m_i, returning (x, product of the m_i)
+pub fn crt(congruences: &[(BigUint, BigUint)]) -> Option<(BigUint, BigUint)> {
+    congruences.iter()
+        .try_fold((BigUint::zero(), BigUint::one()), |(x, modulus), (r, m)| {
+            // x + modulus * t = r (mod m)  =>  t = (r - x) / modulus (mod m)
+            let inverse = invmod(&(&modulus % m), m)?;
+            let diff = (BigInt::from(r.clone()) - BigInt::from(x.clone())).mod_floor(&BigInt::from(m.clone()));
+            let t = diff.to_biguint().unwrap() * inverse % m;
+            Some((x + &modulus * t, modulus * m))
+        })
+}
+
+// floor(x^(1/n)) by Newton's method, starting from a power of two above the root
+pub fn iroot(x: &BigUint, n: u32) -> BigUint {
+    assert!(n > 0, "zeroth root");
+    if x.is_zero() || n == 1 {
+        return x.clone();
+    }
+    let mut y = BigUint::one() << x.bits().div_ceil(n as u64);
+    loop {
+        let z = (&y * (n - 1) + x / y.pow(n - 1)) / n;
+        if z >= y {
+            return y;
+        }
+        y = z;
+    }
+}
+
+pub fn exact_root(x: &BigUint, n: u32) -> Option<BigUint> {
+    let root = iroot(x, n);
+    if &root.pow(n) == x {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+// Sieve of Eratosthenes
+pub fn primes_below(limit: u32) -> Vec<u32> {
+    let mut composite = vec![false; limit as usize];
+    let mut primes = vec![];
+    for i in 2..limit as usize {
+        if !composite[i] {
+            primes.push(i as u32);
+            (i * i..limit as usize).step_by(i).for_each(|j| composite[j] = true);
+        }
+    }
+    primes
+}
+
+pub fn is_probable_prime<R: Rng>(n: &BigUint, rounds: usize, rng: &mut R) -> bool {
+    // Trial division weeds out most candidates far more cheaply than a round of Miller-Rabin
+    for p in primes_below(SIEVE_LIMIT) {
+        if n == &BigUint::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+    if n < &BigUint::from(SIEVE_LIMIT) {
+        return false;
+    }
+
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> s;
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&BigUint::from(2u32), &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+fn random_odd<R: Rng>(bits: u64, rng: &mut R) -> BigUint {
+    rng.gen_biguint(bits) | (BigUint::one() << (bits - 1)) | BigUint::one()
+}
+
+// A random prime with exactly `bits` bits
+pub fn gen_prime<R: Rng>(bits: u64, rng: &mut R) -> BigUint {
+    assert!(bits >= 2, "no primes below 2 bits");
+    if bits == 2 {
+        return BigUint::from(rng.gen_range(2u32, 4));
+    }
+    loop {
+        let candidate = random_odd(bits, rng);
+        if is_probable_prime(&candidate, MR_ROUNDS, rng) {
+            return candidate;
+        }
+    }
+}
+
+// p = 2q + 1 with q prime as well
+pub fn gen_safe_prime<R: Rng>(bits: u64, rng: &mut R) -> BigUint {
+    assert!(bits >= 3, "no safe primes below 3 bits");
+    loop {
+        let q = gen_prime(bits - 1, rng);
+        let p = (q << 1) + 1u32;
+        if is_probable_prime(&p, MR_ROUNDS, rng) {
+            return p;
+        }
+    }
+}
+
+/*
+ * Gordon's algorithm for strong primes: p such that p - 1 has a large prime
+ * factor r, p + 1 has a large prime factor s and r - 1 has a large prime
+ * factor t.
+ *
+ * Pick primes s and t, walk r = 2it + 1 until it's prime, then
+ * p0 = 2(s^(r-2) mod r)s - 1 is 1 mod r and -1 mod s, and so is every
+ * p0 + 2jrs. Walk those until one is prime.
+ */
+pub fn gen_strong_prime<R: Rng>(bits: u64, rng: &mut R) -> BigUint {
+    assert!(bits >= 32, "strong primes need room for their factors");
+    let low = BigUint::one() << (bits - 1);
+    loop {
+        let s = gen_prime(bits * 3 / 8, rng);
+        let t = gen_prime(bits / 4, rng);
+        let mut r = &t * 2u32 + 1u32;
+        while !is_probable_prime(&r, MR_ROUNDS, rng) {
+            r += &t * 2u32;
+        }
+
+        let p0 = s.modpow(&(&r - 2u32), &r) * &s * 2u32 - 1u32;
+        let step = &r * &s * 2u32;
+        let mut p = if p0 >= low {
+            p0
+        } else {
+            let j = (&low - &p0).div_ceil(&step);
+            p0 + j * &step
+        };
+        while p.bits() == bits {
+            if is_probable_prime(&p, MR_ROUNDS, rng) {
+                return p;
+            }
+            p += &step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn challenge39_invmod() {
+        assert_eq!(invmod(&BigUint::from(17u32), &BigUint::from(3120u32)), Some(BigUint::from(2753u32)));
+        assert_eq!(invmod(&BigUint::from(6u32), &BigUint::from(9u32)), None);
+    }
+
+    #[test]
+    fn crt_textbook() {
+        let congruences = [(2u32, 3u32), (3, 5), (2, 7)].iter()
+            .map(|&(r, m)| (BigUint::from(r), BigUint::from(m)))
+            .collect::<Vec<_>>();
+        assert_eq!(crt(&congruences), Some((BigUint::from(23u32), BigUint::from(105u32))));
+        assert_eq!(crt(&[(BigUint::one(), BigUint::from(4u32)), (BigUint::one(), BigUint::from(6u32))]), None);
+    }
+
+    #[test]
+    fn exact_cube_root() {
+        let x = BigUint::parse_bytes(b"123456789123456789123456789", 10).unwrap();
+        assert_eq!(exact_root(&x.pow(3), 3), Some(x.clone()));
+        assert_eq!(exact_root(&(x.pow(3) + 1u32), 3), None);
+        assert_eq!(iroot(&(x.pow(3) - 1u32), 3), x - 1u32);
+    }
+
+    #[test]
+    fn miller_rabin_carmichael() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for &n in &[561u64, 1105, 1729, 2465, 2821, 6601, 8911, 41041, 825265] {
+            assert!(!is_probable_prime(&BigUint::from(n), MR_ROUNDS, &mut rng));
+        }
+        let m127 = (BigUint::one() << 127) - 1u32;
+        assert!(is_probable_prime(&m127, MR_ROUNDS, &mut rng));
+    }
+
+    #[test]
+    fn seeded_generation_is_reproducible() {
+        let p1 = gen_prime(256, &mut StdRng::seed_from_u64(39));
+        let p2 = gen_prime(256, &mut StdRng::seed_from_u64(39));
+        assert_eq!(p1, p2);
+        assert_eq!(p1.bits(), 256);
+    }
+
+    #[test]
+    fn safe_prime() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let p = gen_safe_prime(96, &mut rng);
+        assert_eq!(p.bits(), 96);
+        assert!(is_probable_prime(&((&p - 1u32) >> 1), MR_ROUNDS, &mut rng));
+    }
+
+    #[test]
+    fn strong_prime() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let p = gen_strong_prime(256, &mut rng);
+        assert_eq!(p.bits(), 256);
+        assert!(is_probable_prime(&p, MR_ROUNDS, &mut rng));
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use quickcheck::TestResult;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn big(x: u64) -> BigUint {
+        BigUint::from(x)
+    }
+
+    fn gcd(a: i64, b: i64) -> i64 {
+        if b == 0 { a.abs() } else { gcd(b, a % b) }
+    }
+
+    #[quickcheck]
+    fn modexp_matches_repeated_multiplication(base: u64, exp: u16, modulus: u64) -> TestResult {
+        if modulus == 0 {
+            return TestResult::discard();
+        }
+        let expected = (0..exp).fold(1u128 % modulus as u128, |acc, _| acc * (base % modulus) as u128 % modulus as u128);
+        TestResult::from_bool(modexp(&big(base), &big(exp as u64), &big(modulus)) == big(expected as u64))
+    }
+
+    #[quickcheck]
+    fn egcd_bezout(a: i32, b: i32) -> bool {
+        let (g, x, y) = egcd(&BigInt::from(a), &BigInt::from(b));
+        g == BigInt::from(gcd(a as i64, b as i64)) && BigInt::from(a) * x + BigInt::from(b) * y == g
+    }
+
+    #[quickcheck]
+    fn invmod_matches_search(a: u16, modulus: u16) -> TestResult {
+        if modulus < 2 {
+            return TestResult::discard();
+        }
+        let expected = (1..modulus as u64).find(|x| a as u64 * x % modulus as u64 == 1).map(big);
+        TestResult::from_bool(invmod(&big(a as u64), &big(modulus as u64)) == expected)
+    }
+
+    #[quickcheck]
+    fn crt_matches_search(r1: u8, m1: u8, r2: u8, m2: u8) -> TestResult {
+        if m1 < 2 || m2 < 2 {
+            return TestResult::discard();
+        }
+        let (m1, m2) = (m1 as u64, m2 as u64);
+        let (r1, r2) = (r1 as u64 % m1, r2 as u64 % m2);
+        let expected = if gcd(m1 as i64, m2 as i64) == 1 {
+            (0..m1 * m2).find(|x| x % m1 == r1 && x % m2 == r2).map(|x| (big(x), big(m1 * m2)))
+        } else {
+            None
+        };
+        TestResult::from_bool(crt(&[(big(r1), big(m1)), (big(r2), big(m2))]) == expected)
+    }
+
+    #[quickcheck]
+    fn iroot_brackets_x(x: u64, n: u8) -> bool {
+        let n = (n % 6 + 1) as u32;
+        let root = iroot(&big(x), n);
+        root.pow(n) <= big(x) && (root + 1u32).pow(n) > big(x)
+    }
+
+    #[quickcheck]
+    fn miller_rabin_matches_trial_division(n: u32) -> bool {
+        let n = n as u64;
+        let prime = n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0);
+        is_probable_prime(&big(n), MR_ROUNDS, &mut StdRng::seed_from_u64(n)) == prime
+    }
+}
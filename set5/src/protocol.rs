@@ -0,0 +1,196 @@
+use std::io::{BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/*
+ * In-process protocol harness
+ *
+ * Alice and Bob each run on their own thread and can only talk through an
+ * Endpoint. Every message is routed through the calling thread, which hands it
+ * to an Interceptor first: that's where a man-in-the-middle gets to read a
+ * message, rewrite it or drop it before it reaches the other side. Nothing
+ * touches the network, so protocols and their attacks run fine from tests.
+ *
+ * Parties that should also work over a real socket are written against the
+ * Transport trait instead, which both Endpoint and TcpTransport implement.
+ */
+
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    AliceToBob,
+    BobToAlice,
+}
+
+pub trait Interceptor<M> {
+    // Returning None drops the message on the floor
+    fn intercept(&mut self, direction: Direction, msg: M) -> Option<M>;
+}
+
+// An honest network, delivers everything as is
+pub struct Passthrough;
+
+impl<M> Interceptor<M> for Passthrough {
+    fn intercept(&mut self, _direction: Direction, msg: M) -> Option<M> {
+        Some(msg)
+    }
+}
+
+pub struct Endpoint<M> {
+    direction: Direction,
+    outbox: Sender<(Direction, M)>,
+    inbox: Receiver<M>,
+}
+
+impl<M> Endpoint<M> {
+    pub fn send(&self, msg: M) {
+        // The router outlives both parties, so the only way this fails is if
+        // the other side already hung up, in which case nobody is listening anyway
+        let _ = self.outbox.send((self.direction, msg));
+    }
+
+    // Times out rather than blocking forever when the interceptor drops a message
+    pub fn recv(&self) -> Result<M, RecvTimeoutError> {
+        self.inbox.recv_timeout(RECV_TIMEOUT)
+    }
+}
+
+// What a party needs from the wire, whichever wire that is
+pub trait Transport<M> {
+    fn send(&mut self, msg: M) -> Result<(), String>;
+    fn recv(&mut self) -> Result<M, String>;
+}
+
+impl<M> Transport<M> for Endpoint<M> {
+    fn send(&mut self, msg: M) -> Result<(), String> {
+        Endpoint::send(self, msg);
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<M, String> {
+        Endpoint::recv(self).map_err(|e| e.to_string())
+    }
+}
+
+// Messages that can be sent over a socket, one line each
+pub trait Wire: Sized {
+    fn to_line(&self) -> String;
+    fn from_line(line: &str) -> Result<Self, String>;
+}
+
+pub struct TcpTransport<M> {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    message: PhantomData<M>,
+}
+
+impl<M> TcpTransport<M> {
+    pub fn new(stream: TcpStream) -> Result<Self, String> {
+        stream.set_read_timeout(Some(RECV_TIMEOUT)).map_err(|e| e.to_string())?;
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        Ok(TcpTransport { reader: BufReader::new(stream), writer, message: PhantomData })
+    }
+}
+
+impl<M: Wire> Transport<M> for TcpTransport<M> {
+    fn send(&mut self, msg: M) -> Result<(), String> {
+        writeln!(self.writer, "{}", msg.to_line()).map_err(|e| e.to_string())
+    }
+
+    fn recv(&mut self) -> Result<M, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(String::from("connection closed")),
+            Ok(_) => M::from_line(line.trim_end()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+pub fn run<M, I, A, B, RA, RB>(alice: A, bob: B, mut interceptor: I) -> (RA, RB, I)
+where
+    M: Send + 'static,
+    I: Interceptor<M>,
+    A: FnOnce(Endpoint<M>) -> RA + Send + 'static,
+    B: FnOnce(Endpoint<M>) -> RB + Send + 'static,
+    RA: Send + 'static,
+    RB: Send + 'static,
+{
+    let (outbox, router) = channel();
+    let (to_alice, alice_inbox) = channel();
+    let (to_bob, bob_inbox) = channel();
+
+    let alice_endpoint = Endpoint { direction: Direction::AliceToBob, outbox: outbox.clone(), inbox: alice_inbox };
+    let bob_endpoint = Endpoint { direction: Direction::BobToAlice, outbox, inbox: bob_inbox };
+    let alice = thread::spawn(move || alice(alice_endpoint));
+    let bob = thread::spawn(move || bob(bob_endpoint));
+
+    // The router's channel closes once both parties have returned and dropped their endpoints
+    for (direction, msg) in router {
+        if let Some(msg) = interceptor.intercept(direction, msg) {
+            let _ = match direction {
+                Direction::AliceToBob => to_bob.send(msg),
+                Direction::BobToAlice => to_alice.send(msg),
+            };
+        }
+    }
+
+    (alice.join().expect("alice panicked"), bob.join().expect("bob panicked"), interceptor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shouty {
+        seen: Vec<String>,
+    }
+
+    impl Interceptor<String> for Shouty {
+        fn intercept(&mut self, _direction: Direction, msg: String) -> Option<String> {
+            self.seen.push(msg.clone());
+            if msg == "drop me" {
+                return None;
+            }
+            Some(msg.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn passthrough_delivers() {
+        let (alice, bob, _) = run(
+            |ep: Endpoint<String>| {
+                ep.send("ping".to_string());
+                ep.recv().unwrap()
+            },
+            |ep: Endpoint<String>| {
+                let msg = ep.recv().unwrap();
+                ep.send("pong".to_string());
+                msg
+            },
+            Passthrough,
+        );
+        assert_eq!(alice, "pong");
+        assert_eq!(bob, "ping");
+    }
+
+    #[test]
+    fn interceptor_rewrites_and_drops() {
+        let (alice, bob, shouty) = run(
+            |ep: Endpoint<String>| {
+                ep.send("drop me".to_string());
+                ep.send("hello".to_string());
+                ep.recv().is_err()
+            },
+            |ep: Endpoint<String>| ep.recv().unwrap(),
+            Shouty { seen: vec![] },
+        );
+        assert!(alice);
+        assert_eq!(bob, "HELLO");
+        assert_eq!(shouty.seen, vec!["drop me", "hello"]);
+    }
+}
@@ -0,0 +1,184 @@
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive};
+use rand::Rng;
+
+use crate::numtheory::{crt, exact_root, gen_prime, invmod};
+
+/*
+ * Textbook RSA
+ *
+ * n = pq, d = e^-1 mod (p-1)(q-1), and encryption is just m^e mod n. No
+ * padding whatsoever, which is exactly what the attacks want.
+ */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    pub e: BigUint,
+    pub n: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivateKey {
+    pub d: BigUint,
+    pub n: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    pub public: PublicKey,
+    pub private: PrivateKey,
+}
+
+// A key whose modulus has exactly `bits` bits. `e` is usually 3 or 65537.
+pub fn generate<R: Rng>(bits: u64, e: u32, rng: &mut R) -> KeyPair {
+    let e = BigUint::from(e);
+    // Primes with gcd(e, p - 1) != 1 leave e without an inverse
+    let mut prime = |bits| loop {
+        let p: BigUint = gen_prime(bits, rng);
+        if (&p - 1u32).gcd(&e).is_one() {
+            return p;
+        }
+    };
+    let p = prime(bits - bits / 2);
+    loop {
+        let q = prime(bits / 2);
+        let n = &p * &q;
+        if q == p || n.bits() != bits {
+            continue;
+        }
+        let totient = (&p - 1u32) * (&q - 1u32);
+        let d = invmod(&e, &totient).unwrap();
+        return KeyPair {
+            public: PublicKey { e: e.clone(), n: n.clone() },
+            private: PrivateKey { d, n },
+        };
+    }
+}
+
+impl PublicKey {
+    pub fn encrypt(&self, m: &BigUint) -> BigUint {
+        m.modpow(&self.e, &self.n)
+    }
+
+    pub fn encrypt_bytes(&self, m: &[u8]) -> BigUint {
+        self.encrypt(&bytes_to_int(m))
+    }
+
+    // Length of the modulus in bytes
+    pub fn size(&self) -> usize {
+        self.n.bits().div_ceil(8) as usize
+    }
+}
+
+impl PrivateKey {
+    pub fn decrypt(&self, c: &BigUint) -> BigUint {
+        c.modpow(&self.d, &self.n)
+    }
+
+    pub fn decrypt_bytes(&self, c: &BigUint) -> Vec<u8> {
+        int_to_bytes(&self.decrypt(c), 0)
+    }
+}
+
+pub fn bytes_to_int(bs: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bs)
+}
+
+// Big-endian bytes, left-padded with zeros to at least `len`
+pub fn int_to_bytes(x: &BigUint, len: usize) -> Vec<u8> {
+    let bs = x.to_bytes_be();
+    if bs.len() >= len {
+        return bs;
+    }
+    let mut padded = vec![0; len - bs.len()];
+    padded.extend(bs);
+    padded
+}
+
+/*
+ * Hastad's broadcast attack
+ *
+ * The same m encrypted under e different keys that all share exponent e gives
+ * c_i = m^e mod n_i. CRT glues those into m^e mod (n_1 ... n_e), and since
+ * m < n_i for every i, m^e is smaller than that product, so the CRT result is
+ * m^e over the plain integers. Take the e-th root and we're done.
+ */
+pub fn broadcast_attack(intercepts: &[(BigUint, PublicKey)]) -> Option<BigUint> {
+    let e = &intercepts.first()?.1.e;
+    if intercepts.iter().any(|(_, key)| &key.e != e) {
+        return None;
+    }
+    // There's no zeroth root, and nobody has 2^32 intercepts
+    let e = e.to_u32().filter(|&e| e != 0)?;
+    if intercepts.len() < e as usize {
+        return None;
+    }
+    let congruences = intercepts.iter()
+        .map(|(c, key)| (c.clone(), key.n.clone()))
+        .collect::<Vec<_>>();
+    let (m_e, _) = crt(&congruences)?;
+    exact_root(&m_e, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn byte_conversion() {
+        assert_eq!(int_to_bytes(&bytes_to_int(b"\x00\x01hi"), 4), b"\x00\x01hi");
+        assert_eq!(int_to_bytes(&bytes_to_int(b"\x00\x01hi"), 0), b"\x01hi");
+        assert_eq!(int_to_bytes(&BigUint::from(0x0102u32), 2), vec![1, 2]);
+    }
+
+    #[test]
+    fn challenge39_rsa_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(39);
+        for &(bits, e) in &[(512, 3), (512, 65537), (1024, 3)] {
+            let keys = generate(bits, e, &mut rng);
+            assert_eq!(keys.public.n.bits(), bits);
+            assert_eq!(keys.public.size(), bits as usize / 8);
+            let c = keys.public.encrypt_bytes(b"Shaolin shadowboxing");
+            assert_eq!(keys.private.decrypt_bytes(&c), b"Shaolin shadowboxing");
+        }
+    }
+
+    #[test]
+    fn challenge39_small_numbers() {
+        let keys = KeyPair {
+            public: PublicKey { e: BigUint::from(17u32), n: BigUint::from(3233u32) },
+            private: PrivateKey { d: BigUint::from(2753u32), n: BigUint::from(3233u32) },
+        };
+        let c = keys.public.encrypt(&BigUint::from(42u32));
+        assert_eq!(c, BigUint::from(2557u32));
+        assert_eq!(keys.private.decrypt(&c), BigUint::from(42u32));
+    }
+
+    #[test]
+    fn challenge40_broadcast_attack() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let message = b"Bring the ruckus";
+        let intercepts = (0..3)
+            .map(|_| generate(512, 3, &mut rng).public)
+            .map(|key| (key.encrypt_bytes(message), key))
+            .collect::<Vec<_>>();
+        let recovered = broadcast_attack(&intercepts).unwrap();
+        assert_eq!(int_to_bytes(&recovered, 0), message);
+
+        // two ciphertexts aren't enough for e = 3
+        assert_eq!(broadcast_attack(&intercepts[..2]), None);
+
+        // nor are any for silly exponents
+        let zero = intercepts.iter()
+            .map(|(c, key)| (c.clone(), PublicKey { e: BigUint::from(0u32), n: key.n.clone() }))
+            .collect::<Vec<_>>();
+        assert_eq!(broadcast_attack(&zero), None);
+        let huge = intercepts.iter()
+            .map(|(c, key)| (c.clone(), PublicKey { e: BigUint::one() << 40, n: key.n.clone() }))
+            .collect::<Vec<_>>();
+        assert_eq!(broadcast_attack(&huge), None);
+    }
+}
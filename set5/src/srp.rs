@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use hmac::{Hmac, Mac, NewMac};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::thread_rng;
+use set2::generate_rand;
+use sha2::{Digest, Sha256};
+
+use crate::dh::Group;
+use crate::protocol::{Transport, Wire};
+
+const SALT_SIZE: usize = 16;
+
+/*
+ * Secure Remote Password (SRP-6a)
+ *
+ * The server never stores the password, only a salt and the verifier
+ * v = g^x mod N where x = H(salt || password).
+ *
+ * C -> S  I, A = g^a
+ * S -> C  salt, B = kv + g^b
+ * both    u = H(PAD(A) || PAD(B))
+ * C       S = (B - kg^x)^(a + ux)
+ * S       S = (A v^u)^b
+ * C -> S  HMAC-SHA256(K = H(S), salt)
+ * S -> C  OK or FAIL
+ *
+ * The multiplier is k = H(N || PAD(g)), where PAD left-pads with zeros to the
+ * length of N. That's what makes it 6a: SRP-6 had k = 3.
+ *
+ * The server trusts A. Send it 0, N, 2N, ... and its S is 0 whatever b is, so
+ * a client that knows no password at all can still produce a valid HMAC.
+ */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello { email: String, public: BigUint },
+    Challenge { salt: Vec<u8>, public: BigUint },
+    Proof(Vec<u8>),
+    Verdict(bool),
+}
+
+fn parse_int(hex: &str) -> Result<BigUint, String> {
+    BigUint::parse_bytes(hex.as_bytes(), 16).ok_or_else(|| format!("bad integer: {}", hex))
+}
+
+fn parse_bytes(data: &str) -> Result<Vec<u8>, String> {
+    hex::decode(data).map_err(|e| e.to_string())
+}
+
+impl Wire for Message {
+    fn to_line(&self) -> String {
+        match self {
+            Message::Hello { email, public } => format!("HELLO {} {}", public.to_str_radix(16), email),
+            Message::Challenge { salt, public } => format!("CHALLENGE {} {}", hex::encode(salt), public.to_str_radix(16)),
+            Message::Proof(tag) => format!("PROOF {}", hex::encode(tag)),
+            Message::Verdict(true) => String::from("OK"),
+            Message::Verdict(false) => String::from("FAIL"),
+        }
+    }
+
+    fn from_line(line: &str) -> Result<Self, String> {
+        let parts = line.splitn(3, ' ').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            ["HELLO", public, email] => Ok(Message::Hello { email: email.to_string(), public: parse_int(public)? }),
+            ["CHALLENGE", salt, public] => Ok(Message::Challenge { salt: parse_bytes(salt)?, public: parse_int(public)? }),
+            ["PROOF", tag] => Ok(Message::Proof(parse_bytes(tag)?)),
+            ["OK"] => Ok(Message::Verdict(true)),
+            ["FAIL"] => Ok(Message::Verdict(false)),
+            _ => Err(format!("unexpected message: {}", line)),
+        }
+    }
+}
+
+fn unexpected(msg: Message) -> String {
+    format!("unexpected message: {:?}", msg)
+}
+
+pub fn hash_int(parts: &[&[u8]]) -> BigUint {
+    let mut hasher = Sha256::new();
+    parts.iter().for_each(|part| hasher.update(part));
+    BigUint::from_bytes_be(&hasher.finalize())
+}
+
+pub fn private_key(salt: &[u8], password: &[u8]) -> BigUint {
+    hash_int(&[salt, password])
+}
+
+// PAD() from RFC 5054: big-endian, zero-filled on the left to the length of N
+fn pad(x: &BigUint, group: &Group) -> Vec<u8> {
+    let bs = x.to_bytes_be();
+    let len = (group.p.bits() as usize).div_ceil(8);
+    let mut padded = vec![0; len.saturating_sub(bs.len())];
+    padded.extend(bs);
+    padded
+}
+
+pub fn multiplier(group: &Group) -> BigUint {
+    hash_int(&[&group.p.to_bytes_be(), &pad(&group.g, group)])
+}
+
+pub fn scrambler(group: &Group, client_public: &BigUint, server_public: &BigUint) -> BigUint {
+    hash_int(&[&pad(client_public, group), &pad(server_public, group)])
+}
+
+fn mac(secret: &BigUint) -> Hmac<Sha256> {
+    let key = Sha256::digest(&secret.to_bytes_be());
+    Hmac::<Sha256>::new_varkey(&key).unwrap()
+}
+
+// HMAC-SHA256(SHA256(S), salt), what the client sends to prove it knows S
+pub fn proof(secret: &BigUint, salt: &[u8]) -> Vec<u8> {
+    let mut mac = mac(secret);
+    mac.update(salt);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Constant time, unlike comparing against proof()
+pub fn check_proof(secret: &BigUint, salt: &[u8], tag: &[u8]) -> bool {
+    let mut mac = mac(secret);
+    mac.update(salt);
+    mac.verify(tag).is_ok()
+}
+
+struct Record {
+    salt: Vec<u8>,
+    verifier: BigUint,
+}
+
+pub struct Server {
+    group: Group,
+    users: HashMap<String, Record>,
+    validate_public: bool,
+}
+
+impl Server {
+    pub fn new(group: Group) -> Self {
+        Server { group, users: HashMap::new(), validate_public: false }
+    }
+
+    // A server that refuses client public keys that are 0 mod N
+    pub fn validating(group: Group) -> Self {
+        Server { validate_public: true, ..Server::new(group) }
+    }
+
+    pub fn register(&mut self, email: &str, password: &[u8]) {
+        let salt = generate_rand(SALT_SIZE);
+        let verifier = self.group.g.modpow(&private_key(&salt, password), &self.group.p);
+        self.users.insert(email.to_string(), Record { salt, verifier });
+    }
+
+    // Handles a single login attempt, returning whether the client proved itself
+    pub fn serve<T: Transport<Message>>(&self, transport: &mut T) -> Result<bool, String> {
+        let n = &self.group.p;
+        let (email, client_public) = match transport.recv()? {
+            Message::Hello { email, public } => (email, public),
+            msg => return Err(unexpected(msg)),
+        };
+        let record = match self.users.get(&email) {
+            Some(record) if !(self.validate_public && (&client_public % n).is_zero()) => record,
+            _ => {
+                transport.send(Message::Verdict(false))?;
+                return Ok(false);
+            },
+        };
+
+        let b = thread_rng().gen_biguint_range(&BigUint::one(), n);
+        let server_public = (&record.verifier * multiplier(&self.group) + self.group.g.modpow(&b, n)) % n;
+        transport.send(Message::Challenge { salt: record.salt.clone(), public: server_public.clone() })?;
+
+        let u = scrambler(&self.group, &client_public, &server_public);
+        let secret = (client_public * record.verifier.modpow(&u, n)).modpow(&b, n);
+        let verdict = match transport.recv()? {
+            Message::Proof(tag) => check_proof(&secret, &record.salt, &tag),
+            msg => return Err(unexpected(msg)),
+        };
+        transport.send(Message::Verdict(verdict))?;
+        Ok(verdict)
+    }
+}
+
+fn finish<T: Transport<Message>>(transport: &mut T, secret: &BigUint, salt: &[u8]) -> Result<bool, String> {
+    transport.send(Message::Proof(proof(secret, salt)))?;
+    match transport.recv()? {
+        Message::Verdict(verdict) => Ok(verdict),
+        msg => Err(unexpected(msg)),
+    }
+}
+
+pub fn login<T: Transport<Message>>(transport: &mut T, group: &Group, email: &str, password: &[u8]) -> Result<bool, String> {
+    let n = &group.p;
+    let keys = group.generate_keypair();
+    transport.send(Message::Hello { email: email.to_string(), public: keys.public.clone() })?;
+    let (salt, server_public) = match transport.recv()? {
+        Message::Challenge { salt, public } => (salt, public),
+        Message::Verdict(verdict) => return Ok(verdict),
+        msg => return Err(unexpected(msg)),
+    };
+
+    let u = scrambler(group, &keys.public, &server_public);
+    let x = private_key(&salt, password);
+    // Add N before subtracting so the base can't go negative
+    let base = (server_public + n - group.g.modpow(&x, n) * multiplier(group) % n) % n;
+    let secret = base.modpow(&(keys.private + u * x), n);
+    finish(transport, &secret, &salt)
+}
+
+// Claims to have public key `multiple` * N, which pins the server's S to 0
+pub fn login_without_password<T: Transport<Message>>(transport: &mut T, group: &Group, email: &str, multiple: u32) -> Result<bool, String> {
+    transport.send(Message::Hello { email: email.to_string(), public: &group.p * multiple })?;
+    let salt = match transport.recv()? {
+        Message::Challenge { salt, .. } => salt,
+        Message::Verdict(verdict) => return Ok(verdict),
+        msg => return Err(unexpected(msg)),
+    };
+    finish(transport, &BigUint::zero(), &salt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{run, Endpoint, Passthrough, TcpTransport};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    const EMAIL: &str = "ghostface@killah.com";
+    const PASSWORD: &[u8] = b"ironman";
+
+    fn server() -> Server {
+        let mut server = Server::new(Group::nist());
+        server.register(EMAIL, PASSWORD);
+        server
+    }
+
+    fn attempt<F>(server: Server, client: F) -> (bool, bool)
+    where
+        F: FnOnce(&mut Endpoint<Message>) -> Result<bool, String> + Send + 'static,
+    {
+        let (client, server, _) = run(
+            move |mut ep| client(&mut ep),
+            move |mut ep| server.serve(&mut ep),
+            Passthrough,
+        );
+        (client.unwrap(), server.unwrap())
+    }
+
+    #[test]
+    fn wire_roundtrip() {
+        let msgs = vec![
+            Message::Hello { email: String::from("a b@c"), public: BigUint::from(0xdeadbeefu32) },
+            Message::Challenge { salt: vec![1, 2, 3], public: BigUint::zero() },
+            Message::Proof(vec![0xff; 32]),
+            Message::Verdict(true),
+            Message::Verdict(false),
+        ];
+        for msg in msgs {
+            assert_eq!(Message::from_line(&msg.to_line()).unwrap(), msg);
+        }
+        assert!(Message::from_line("HELO").is_err());
+    }
+
+    #[test]
+    fn challenge36_srp_login() {
+        assert_eq!(attempt(server(), |ep| login(ep, &Group::nist(), EMAIL, PASSWORD)), (true, true));
+        assert_eq!(attempt(server(), |ep| login(ep, &Group::nist(), EMAIL, b"iceman")), (false, false));
+        assert_eq!(attempt(server(), |ep| login(ep, &Group::nist(), "raekwon@chef.com", PASSWORD)), (false, false));
+    }
+
+    #[test]
+    fn challenge36_srp_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = server();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            server.serve(&mut TcpTransport::new(stream).unwrap())
+        });
+        let mut transport = TcpTransport::new(TcpStream::connect(addr).unwrap()).unwrap();
+        assert!(login(&mut transport, &Group::nist(), EMAIL, PASSWORD).unwrap());
+        assert!(handle.join().unwrap().unwrap());
+    }
+
+    #[test]
+    fn challenge37_zero_key_bypass() {
+        for multiple in 0..3 {
+            let outcome = attempt(server(), move |ep| login_without_password(ep, &Group::nist(), EMAIL, multiple));
+            assert_eq!(outcome, (true, true));
+        }
+    }
+
+    #[test]
+    fn challenge37_validating_server() {
+        for multiple in 0..3 {
+            let mut server = Server::validating(Group::nist());
+            server.register(EMAIL, PASSWORD);
+            let outcome = attempt(server, move |ep| login_without_password(ep, &Group::nist(), EMAIL, multiple));
+            assert_eq!(outcome, (false, false));
+        }
+        let mut server = Server::validating(Group::nist());
+        server.register(EMAIL, PASSWORD);
+        assert_eq!(attempt(server, |ep| login(ep, &Group::nist(), EMAIL, PASSWORD)), (true, true));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::One;
+use rand::thread_rng;
+use rayon::prelude::*;
+use set2::generate_rand;
+
+use crate::dh::Group;
+use crate::protocol::Transport;
+use crate::srp::{check_proof, private_key, proof};
+
+const SALT_SIZE: usize = 16;
+const SCRAMBLER_BITS: u64 = 128;
+
+/*
+ * Simplified SRP
+ *
+ * C -> S  I, A = g^a
+ * S -> C  salt, B = g^b, u (random 128 bits)
+ * C       S = B^(a + ux)
+ * S       S = (A v^u)^b
+ * C -> S  HMAC-SHA256(K = H(S), salt)
+ * S -> C  OK or FAIL
+ *
+ * With B no longer mixing in the verifier, the client can't tell a real server
+ * from one that merely picks b, u and salt. An impostor choosing b = 1, u = 1
+ * and an empty salt gets back an HMAC keyed off S = A g^x, where the only
+ * unknown is x = H(password). That's an offline dictionary attack.
+ */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello { email: String, public: BigUint },
+    Challenge { salt: Vec<u8>, public: BigUint, u: BigUint },
+    Proof(Vec<u8>),
+    Verdict(bool),
+}
+
+fn unexpected(msg: Message) -> String {
+    format!("unexpected message: {:?}", msg)
+}
+
+pub struct Server {
+    group: Group,
+    users: HashMap<String, (Vec<u8>, BigUint)>,
+}
+
+impl Server {
+    pub fn new(group: Group) -> Self {
+        Server { group, users: HashMap::new() }
+    }
+
+    pub fn register(&mut self, email: &str, password: &[u8]) {
+        let salt = generate_rand(SALT_SIZE);
+        let verifier = self.group.g.modpow(&private_key(&salt, password), &self.group.p);
+        self.users.insert(email.to_string(), (salt, verifier));
+    }
+
+    pub fn serve<T: Transport<Message>>(&self, transport: &mut T) -> Result<bool, String> {
+        let n = &self.group.p;
+        let (email, client_public) = match transport.recv()? {
+            Message::Hello { email, public } => (email, public),
+            msg => return Err(unexpected(msg)),
+        };
+        let (salt, verifier) = match self.users.get(&email) {
+            Some(record) => record,
+            None => {
+                transport.send(Message::Verdict(false))?;
+                return Ok(false);
+            },
+        };
+
+        let keys = self.group.generate_keypair();
+        let u = thread_rng().gen_biguint(SCRAMBLER_BITS);
+        transport.send(Message::Challenge { salt: salt.clone(), public: keys.public, u: u.clone() })?;
+
+        let secret = (client_public * verifier.modpow(&u, n)).modpow(&keys.private, n);
+        let verdict = match transport.recv()? {
+            Message::Proof(tag) => check_proof(&secret, salt, &tag),
+            msg => return Err(unexpected(msg)),
+        };
+        transport.send(Message::Verdict(verdict))?;
+        Ok(verdict)
+    }
+}
+
+pub fn login<T: Transport<Message>>(transport: &mut T, group: &Group, email: &str, password: &[u8]) -> Result<bool, String> {
+    let keys = group.generate_keypair();
+    transport.send(Message::Hello { email: email.to_string(), public: keys.public.clone() })?;
+    let (salt, server_public, u) = match transport.recv()? {
+        Message::Challenge { salt, public, u } => (salt, public, u),
+        Message::Verdict(verdict) => return Ok(verdict),
+        msg => return Err(unexpected(msg)),
+    };
+
+    let x = private_key(&salt, password);
+    let secret = server_public.modpow(&(keys.private + u * x), &group.p);
+    transport.send(Message::Proof(proof(&secret, &salt)))?;
+    match transport.recv()? {
+        Message::Verdict(verdict) => Ok(verdict),
+        msg => Err(unexpected(msg)),
+    }
+}
+
+// Everything the impostor walks away with from a single login attempt
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub client_public: BigUint,
+    pub tag: Vec<u8>,
+}
+
+pub struct Impostor {
+    group: Group,
+}
+
+impl Impostor {
+    pub fn new(group: Group) -> Self {
+        Impostor { group }
+    }
+
+    // Plays the server with b = 1, u = 1 and salt = "", then turns the client away
+    pub fn serve<T: Transport<Message>>(&self, transport: &mut T) -> Result<Capture, String> {
+        let client_public = match transport.recv()? {
+            Message::Hello { public, .. } => public,
+            msg => return Err(unexpected(msg)),
+        };
+        transport.send(Message::Challenge { salt: vec![], public: self.group.g.clone(), u: BigUint::one() })?;
+        let tag = match transport.recv()? {
+            Message::Proof(tag) => tag,
+            msg => return Err(unexpected(msg)),
+        };
+        transport.send(Message::Verdict(false))?;
+        Ok(Capture { client_public, tag })
+    }
+}
+
+// S = B^(a + ux) = g^(a + x) = A g^x, so each guess costs a single modexp
+pub fn crack(group: &Group, capture: &Capture, wordlist: &[String]) -> Option<String> {
+    wordlist.par_iter()
+        .find_any(|word| {
+            let x = private_key(&[], word.as_bytes());
+            let secret = (&capture.client_public * group.g.modpow(&x, &group.p)) % &group.p;
+            proof(&secret, &[]) == capture.tag
+        })
+        .cloned()
+}
+
+// Lowercased, de-duplicated words from a corpus like set1's muchadoaboutnothing.txt
+pub fn load_wordlist(path: &str) -> io::Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    Ok(fs::read_to_string(path)?
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .filter(|word| seen.insert(word.clone()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{run, Passthrough};
+
+    const EMAIL: &str = "dogberry@messina.it";
+
+    #[test]
+    fn challenge38_simplified_srp_login() {
+        let mut server = Server::new(Group::nist());
+        server.register(EMAIL, b"benedick");
+        let (client, server, _) = run(
+            |mut ep| login(&mut ep, &Group::nist(), EMAIL, b"benedick"),
+            move |mut ep| server.serve(&mut ep),
+            Passthrough,
+        );
+        assert!(client.unwrap());
+        assert!(server.unwrap());
+    }
+
+    #[test]
+    fn challenge38_load_wordlist() {
+        let words = load_wordlist("../set1/muchadoaboutnothing.txt").unwrap();
+        assert!(words.contains(&String::from("benedick")));
+        assert_eq!(words.iter().collect::<HashSet<_>>().len(), words.len());
+        assert!(words.iter().all(|word| word.chars().all(char::is_lowercase)));
+    }
+
+    #[test]
+    fn challenge38_offline_dictionary_attack() {
+        let wordlist = load_wordlist("../set1/muchadoaboutnothing.txt").unwrap();
+        let password = wordlist[wordlist.len() / 2].clone();
+        let impostor = Impostor::new(Group::nist());
+        let (client, capture, _) = run(
+            move |mut ep| login(&mut ep, &Group::nist(), EMAIL, password.as_bytes()),
+            move |mut ep| impostor.serve(&mut ep),
+            Passthrough,
+        );
+        assert!(!client.unwrap());
+        let cracked = crack(&Group::nist(), &capture.unwrap(), &wordlist);
+        assert_eq!(cracked, Some(wordlist[wordlist.len() / 2].clone()));
+    }
+}
@@ -0,0 +1,256 @@
+use std::cell::Cell;
+use std::cmp::{max, min};
+
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::One;
+use rand::Rng;
+use set5::numtheory::invmod;
+use set5::rsa::{int_to_bytes, KeyPair, PublicKey};
+
+use crate::pkcs1::unpad_encryption;
+
+/*
+ * Bleichenbacher's PKCS#1 v1.5 padding oracle (CRYPTO '98)
+ *
+ * A conforming plaintext starts 00 02, so with B = 2^(8(k-2)) it lies in
+ * [2B, 3B). Whenever c s^e is also conforming, ms mod n lands in that window
+ * too, which bounds m to a handful of intervals. Keep finding such s, each one
+ * a bit bigger than the last, and the intervals shrink down to m itself.
+ *
+ * Step 1 blinds c until it conforms (a no-op for real ciphertexts), step 2
+ * searches for the next s, step 3 narrows the intervals and step 4 stops once
+ * there's a single point left.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conformance {
+    // Only checks for the leading 00 02
+    Prefix,
+    // Also insists on eight non-zero padding bytes and a zero separator
+    Strict,
+}
+
+pub struct PaddingOracle {
+    keys: KeyPair,
+    conformance: Conformance,
+    queries: Cell<usize>,
+}
+
+impl PaddingOracle {
+    pub fn new(keys: KeyPair, conformance: Conformance) -> Self {
+        PaddingOracle { keys, conformance, queries: Cell::new(0) }
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.keys.public
+    }
+
+    pub fn is_conforming(&self, c: &BigUint) -> bool {
+        self.queries.set(self.queries.get() + 1);
+        let em = int_to_bytes(&self.keys.private.decrypt(c), self.keys.public.size());
+        match self.conformance {
+            Conformance::Prefix => em[0] == 0x00 && em[1] == 0x02,
+            Conformance::Strict => unpad_encryption(&em).is_ok(),
+        }
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+type Interval = (BigUint, BigUint);
+
+struct Attack<'a> {
+    oracle: &'a PaddingOracle,
+    c0: BigUint,
+    e: BigUint,
+    n: BigUint,
+    b2: BigUint,
+    b3: BigUint,
+}
+
+impl Attack<'_> {
+    fn conforming(&self, s: &BigUint) -> bool {
+        self.oracle.is_conforming(&(&self.c0 * s.modpow(&self.e, &self.n) % &self.n))
+    }
+
+    // Step 2a and 2b: the smallest conforming s from `from` upwards
+    fn search_from(&self, from: BigUint) -> BigUint {
+        let mut s = from;
+        while !self.conforming(&s) {
+            s += 1u32;
+        }
+        s
+    }
+
+    // Step 2c: with a single interval [a, b] left, pick r and only try the s
+    // that would put ms back in [2B, 3B), which roughly halves the interval each time
+    fn search_single(&self, (a, b): &Interval, prev: &BigUint) -> BigUint {
+        let n = &self.n;
+        let mut r = ((b * prev - &self.b2) * 2u32).div_ceil(n);
+        loop {
+            let low = (&self.b2 + &r * n).div_ceil(b);
+            let high = (&self.b3 + &r * n).div_ceil(a);
+            let mut s = low;
+            while s < high {
+                if self.conforming(&s) {
+                    return s;
+                }
+                s += 1u32;
+            }
+            r += 1u32;
+        }
+    }
+
+    // Step 3: keep the parts of each interval where ms - rn lands in [2B, 3B)
+    fn narrow(&self, intervals: &[Interval], s: &BigUint) -> Vec<Interval> {
+        let n = &self.n;
+        let mut narrowed = vec![];
+        for (a, b) in intervals {
+            let top = &self.b3 - 1u32;
+            let mut r = if a * s > top { (a * s - &top).div_ceil(n) } else { BigUint::from(0u32) };
+            let r_max = (b * s - &self.b2) / n;
+            while r <= r_max {
+                let low = max(a.clone(), (&self.b2 + &r * n).div_ceil(s));
+                let high = min(b.clone(), (&top + &r * n) / s);
+                if low <= high {
+                    narrowed.push((low, high));
+                }
+                r += 1u32;
+            }
+        }
+        merge(narrowed)
+    }
+}
+
+fn merge(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+    let mut merged: Vec<Interval> = vec![];
+    for (a, b) in intervals {
+        match merged.last_mut() {
+            Some(last) if a <= last.1 => last.1 = max(last.1.clone(), b),
+            _ => merged.push((a, b)),
+        }
+    }
+    merged
+}
+
+// Recovers the padded plaintext block behind `c`, strip it with pkcs1::unpad_encryption
+pub fn attack<R: Rng>(oracle: &PaddingOracle, c: &BigUint, rng: &mut R) -> Option<BigUint> {
+    let PublicKey { e, n } = oracle.public().clone();
+    let k = oracle.public().size();
+    let b = BigUint::one() << (8 * (k - 2));
+
+    // Step 1: blinding
+    let (c0, s0) = if oracle.is_conforming(c) {
+        (c.clone(), BigUint::one())
+    } else {
+        loop {
+            let s0 = rng.gen_biguint_range(&BigUint::from(2u32), &n);
+            let c0 = c * s0.modpow(&e, &n) % &n;
+            if oracle.is_conforming(&c0) {
+                break (c0, s0);
+            }
+        }
+    };
+    let attack = Attack { oracle, c0, e, n: n.clone(), b2: &b * 2u32, b3: &b * 3u32 };
+
+    let mut intervals = vec![(attack.b2.clone(), &attack.b3 - 1u32)];
+    let mut s = attack.search_from(n.div_ceil(&attack.b3));
+    loop {
+        intervals = attack.narrow(&intervals, &s);
+        match intervals.as_slice() {
+            [] => return None,
+            [(a, b)] if a == b => return Some(a * invmod(&s0, &n)? % &n),
+            [interval] => s = attack.search_single(interval, &s),
+            _ => s = attack.search_from(s + 1u32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkcs1::pad_encryption;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::rsa::{bytes_to_int, generate};
+
+    fn roundtrip(bits: u64, conformance: Conformance, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let oracle = PaddingOracle::new(generate(bits, 3, &mut rng), conformance);
+        let k = oracle.public().size();
+        let message = b"kick it, CC";
+        let c = oracle.public().encrypt(&bytes_to_int(&pad_encryption(message, k, &mut rng).unwrap()));
+
+        let m = attack(&oracle, &c, &mut rng).unwrap();
+        println!("{} bits, {:?}: {} queries", bits, conformance, oracle.queries());
+        assert_eq!(unpad_encryption(&int_to_bytes(&m, k)).unwrap(), message);
+    }
+
+    #[test]
+    fn merging_intervals() {
+        let big = |a: u32, b: u32| (BigUint::from(a), BigUint::from(b));
+        assert_eq!(merge(vec![big(5, 9), big(1, 3), big(2, 6), big(11, 12)]), vec![big(1, 9), big(11, 12)]);
+    }
+
+    #[test]
+    fn strict_conformance() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let keys = generate(256, 3, &mut rng);
+        let k = keys.public.size();
+        let prefix = PaddingOracle::new(keys.clone(), Conformance::Prefix);
+        let strict = PaddingOracle::new(keys, Conformance::Strict);
+        let em = |body: &[u8]| {
+            let mut em = vec![0x00, 0x02];
+            em.extend_from_slice(body);
+            em.resize(k, 0xff);
+            bytes_to_int(&em)
+        };
+        // (encoded message, prefix accepts, strict accepts)
+        let cases = vec![
+            (bytes_to_int(&pad_encryption(b"kick it", k, &mut rng).unwrap()), true, true),
+            // Eight padding bytes is the least allowed
+            (em(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]), true, true),
+            (em(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]), true, false),
+            (em(&[0x00]), true, false),
+            // No zero separator anywhere
+            (em(&[]), true, false),
+            (bytes_to_int(&[0x01, 0xff, 0x00, 0x41]), false, false),
+        ];
+        for (m, prefix_ok, strict_ok) in cases {
+            let c = strict.public().encrypt(&m);
+            assert_eq!(prefix.is_conforming(&c), prefix_ok);
+            assert_eq!(strict.is_conforming(&c), strict_ok);
+        }
+    }
+
+    #[test]
+    fn challenge47_256_bit_key() {
+        roundtrip(256, Conformance::Prefix, 47);
+    }
+
+    // Well over a million queries with a modulus this small, run it with --ignored
+    #[test]
+    #[ignore]
+    fn challenge47_strict_oracle() {
+        roundtrip(256, Conformance::Strict, 47);
+    }
+
+    #[test]
+    fn challenge48_768_bit_key() {
+        roundtrip(768, Conformance::Prefix, 48);
+    }
+
+    #[test]
+    fn challenge48_blinding() {
+        // A ciphertext that doesn't conform to start with forces step 1
+        let mut rng = StdRng::seed_from_u64(48);
+        let oracle = PaddingOracle::new(generate(256, 3, &mut rng), Conformance::Prefix);
+        let m = bytes_to_int(b"not padded at all");
+        let c = oracle.public().encrypt(&m);
+        assert_eq!(attack(&oracle, &c, &mut rng), Some(m));
+    }
+}
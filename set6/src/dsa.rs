@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::numtheory::invmod;
+use sha1::{Digest, Sha1};
+
+/*
+ * DSA
+ *
+ * Signing picks a nonce k and computes
+ *   r = (g^k mod p) mod q
+ *   s = k^-1 (H(m) + xr) mod q
+ * and verification checks that (g^(H(m)/s) y^(r/s) mod p) mod q comes back to r.
+ * Everything rests on k: leak it, make it guessable or use it twice and x
+ * falls out with a little algebra.
+ */
+
+const P: &str = "\
+    800000000000000089e1855218a0e7dac38136ffafa72eda7859f2171e25e65e\
+    ac698c1702578b07dc2a1076da241c76c62d374d8389ea5aeffd3226a0530cc5\
+    65f3bf6b50929139ebeac04f48c3c84afb796d61e5a4f9a8fda812ab59494232\
+    c7d2b4deb50aa18ee9e132bfa85ac4374d7f9091abc3d015efc871a584471bb1";
+const Q: &str = "f4f47f05794b256174bba6e9b396a7707e563c5b";
+const G: &str = "\
+    5958c9d3898b224b12672c0b98e06c60df923cb8bc999d119458fef538b8fa40\
+    46c8db53039db620c094c9fa077ef389b5322a559946a71903f990f1f7e0e025\
+    e2d7f7cf494aff1a0470f5b64c36b625a097f1651fe775323556fe00b3608c88\
+    7892878480e99041be601a62166ca6894bdd41a7054ec89f756ba9fc95302291";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedMessage {
+    pub message: Vec<u8>,
+    pub signature: Signature,
+}
+
+pub fn hash_message(message: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&Sha1::digest(message))
+}
+
+fn hex(s: &str) -> BigUint {
+    BigUint::parse_bytes(s.as_bytes(), 16).unwrap()
+}
+
+impl Params {
+    pub fn new(p: BigUint, q: BigUint, g: BigUint) -> Self {
+        Params { p, q, g }
+    }
+
+    // The 1024/160 bit group from the Cryptopals challenges
+    pub fn cryptopals() -> Self {
+        Params::new(hex(P), hex(Q), hex(G))
+    }
+
+    pub fn generate_keypair<R: Rng>(&self, rng: &mut R) -> KeyPair {
+        let x = rng.gen_biguint_range(&BigUint::one(), &self.q);
+        self.keypair_from_private(x)
+    }
+
+    pub fn keypair_from_private(&self, x: BigUint) -> KeyPair {
+        let y = self.g.modpow(&x, &self.p);
+        KeyPair { x, y }
+    }
+
+    // No sanity checks on r or s, so tampered parameters sign happily
+    pub fn sign_with_nonce(&self, x: &BigUint, h: &BigUint, k: &BigUint) -> Option<Signature> {
+        let r = self.g.modpow(k, &self.p) % &self.q;
+        let s = invmod(k, &self.q)? * (h + x * &r) % &self.q;
+        Some(Signature { r, s })
+    }
+
+    pub fn sign<R: Rng>(&self, x: &BigUint, message: &[u8], rng: &mut R) -> Signature {
+        let h = hash_message(message);
+        loop {
+            let k = rng.gen_biguint_range(&BigUint::one(), &self.q);
+            match self.sign_with_nonce(x, &h, &k) {
+                Some(sig) if !sig.r.is_zero() && !sig.s.is_zero() => return sig,
+                _ => continue,
+            }
+        }
+    }
+
+    pub fn verify(&self, y: &BigUint, message: &[u8], sig: &Signature) -> bool {
+        let in_range = |v: &BigUint| !v.is_zero() && v < &self.q;
+        in_range(&sig.r) && in_range(&sig.s) && self.verify_unchecked(y, message, sig)
+    }
+
+    // Skips the 0 < r, s < q checks, which is what lets g = 0 through
+    pub fn verify_unchecked(&self, y: &BigUint, message: &[u8], sig: &Signature) -> bool {
+        let w = match invmod(&sig.s, &self.q) {
+            Some(w) => w,
+            None => return false,
+        };
+        let u1 = hash_message(message) * &w % &self.q;
+        let u2 = &sig.r * &w % &self.q;
+        let v = self.g.modpow(&u1, &self.p) * y.modpow(&u2, &self.p) % &self.p % &self.q;
+        v == sig.r
+    }
+
+    // x = (sk - H(m)) / r mod q
+    pub fn private_from_nonce(&self, h: &BigUint, sig: &Signature, k: &BigUint) -> Option<BigUint> {
+        let q = &self.q;
+        let sk = &sig.s * k % q;
+        Some((sk + q - h % q) % q * invmod(&sig.r, q)? % q)
+    }
+}
+
+/*
+ * Challenge 43: the nonce only has 16 bits of entropy. Walk g^k one
+ * multiplication at a time until it matches r, then solve for x.
+ */
+pub fn recover_from_weak_nonce(params: &Params, y: &BigUint, message: &[u8], sig: &Signature, max_k: u64) -> Option<BigUint> {
+    let h = hash_message(message);
+    let mut gk = BigUint::one();
+    for k in 1..=max_k {
+        gk = gk * &params.g % &params.p;
+        if &gk % &params.q != sig.r {
+            continue;
+        }
+        let x = params.private_from_nonce(&h, sig, &BigUint::from(k))?;
+        if &params.g.modpow(&x, &params.p) == y {
+            return Some(x);
+        }
+    }
+    None
+}
+
+/*
+ * Challenge 44: two signatures with the same k share an r, and
+ *   s1 - s2 = k^-1 (H(m1) - H(m2))  =>  k = (H(m1) - H(m2)) / (s1 - s2) mod q
+ */
+pub fn recover_from_repeated_nonce(params: &Params, y: &BigUint, signed: &[SignedMessage]) -> Option<BigUint> {
+    let q = &params.q;
+    let mut by_r: HashMap<&BigUint, &SignedMessage> = HashMap::new();
+    for second in signed {
+        let first = match by_r.insert(&second.signature.r, second) {
+            Some(first) if first.signature.s != second.signature.s => first,
+            _ => continue,
+        };
+        let (h1, h2) = (hash_message(&first.message) % q, hash_message(&second.message) % q);
+        let ds = (&first.signature.s + q - &second.signature.s) % q;
+        let k = (h1 + q - &h2) % q * invmod(&ds, q)? % q;
+        let x = params.private_from_nonce(&h2, &second.signature, &k)?;
+        if &params.g.modpow(&x, &params.p) == y {
+            return Some(x);
+        }
+    }
+    None
+}
+
+/*
+ * Challenge 45: with g = p + 1 every power of g is 1 mod p. Pick any z and set
+ *   r = (y^z mod p) mod q,   s = r / z mod q
+ * then g^u1 y^u2 = y^(r/s) = y^z, which is r. Good for every message.
+ */
+pub fn magic_signature(params: &Params, y: &BigUint, z: &BigUint) -> Option<Signature> {
+    let r = y.modpow(z, &params.p) % &params.q;
+    let s = &r * invmod(z, &params.q)? % &params.q;
+    Some(Signature { r, s })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const CHALLENGE43_Y: &str = "\
+        84ad4719d044495496a3201c8ff484feb45b962e7302e56a392aee4abab3e4bd\
+        ebf2955b4736012f21a08084056b19bcd7fee56048e004e44984e2f411788efd\
+        c837a0d2e5abb7b555039fd243ac01f0fb2ed1dec568280ce678e931868d23eb\
+        095fde9d3779191b8c0299d6e07bbb283e6633451e535c45513b2d33c99ea17";
+    const CHALLENGE43_MESSAGE: &[u8] = b"For those that envy a MC it can be hazardous to your health\n\
+        So be friendly, a matter of life and death, just like a etch-a-sketch\n";
+
+    fn dec(s: &str) -> BigUint {
+        BigUint::parse_bytes(s.as_bytes(), 10).unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let params = Params::cryptopals();
+        let keys = params.generate_keypair(&mut rng);
+        let sig = params.sign(&keys.x, b"Protect ya neck", &mut rng);
+        assert!(params.verify(&keys.y, b"Protect ya neck", &sig));
+        assert!(!params.verify(&keys.y, b"Protect ya face", &sig));
+    }
+
+    #[test]
+    fn challenge43_weak_nonce() {
+        let params = Params::cryptopals();
+        let y = hex(CHALLENGE43_Y);
+        let sig = Signature {
+            r: dec("548099063082341131477253921760299949438196259240"),
+            s: dec("857042759984254168557880549501802188789837994940"),
+        };
+        assert_eq!(hash_message(CHALLENGE43_MESSAGE), hex("d2d0714f014a9784047eaeccf956520045c45265"));
+        assert!(params.verify(&y, CHALLENGE43_MESSAGE, &sig));
+
+        let x = recover_from_weak_nonce(&params, &y, CHALLENGE43_MESSAGE, &sig, 1 << 16).unwrap();
+        assert_eq!(Sha1::digest(x.to_str_radix(16).as_bytes()).to_vec(),
+                   hex("0954edd5e0afe5542a4adf012611a91912a3ec16").to_bytes_be());
+    }
+
+    #[test]
+    fn challenge44_repeated_nonce() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let params = Params::cryptopals();
+        let keys = params.generate_keypair(&mut rng);
+        let reused = rng.gen_biguint_range(&BigUint::one(), &params.q);
+        let signed = (0..11)
+            .map(|i| {
+                let message = format!("Listen for me, you better listen for me now. {}", i).into_bytes();
+                let signature = if i % 4 == 1 {
+                    params.sign_with_nonce(&keys.x, &hash_message(&message), &reused).unwrap()
+                } else {
+                    params.sign(&keys.x, &message, &mut rng)
+                };
+                SignedMessage { message, signature }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(recover_from_repeated_nonce(&params, &keys.y, &signed), Some(keys.x));
+        assert_eq!(recover_from_repeated_nonce(&params, &keys.y, &signed[..2]), None);
+    }
+
+    #[test]
+    fn challenge45_g_is_zero() {
+        let mut rng = StdRng::seed_from_u64(45);
+        let honest = Params::cryptopals();
+        let params = Params { g: BigUint::zero(), ..honest.clone() };
+        let keys = params.generate_keypair(&mut rng);
+        let k = rng.gen_biguint_range(&BigUint::one(), &params.q);
+        let sig = params.sign_with_nonce(&keys.x, &hash_message(b"Hello, world"), &k).unwrap();
+        assert!(sig.r.is_zero());
+        assert!(params.verify_unchecked(&keys.y, b"Hello, world", &sig));
+        assert!(params.verify_unchecked(&keys.y, b"Goodbye, world", &sig));
+        assert!(!params.verify(&keys.y, b"Goodbye, world", &sig));
+    }
+
+    #[test]
+    fn challenge45_g_is_p_plus_one() {
+        let mut rng = StdRng::seed_from_u64(45);
+        let honest = Params::cryptopals();
+        let keys = honest.generate_keypair(&mut rng);
+        let params = Params { g: &honest.p + 1u32, ..honest };
+        let sig = magic_signature(&params, &keys.y, &BigUint::from(7u32)).unwrap();
+        assert!(params.verify(&keys.y, b"Hello, world", &sig));
+        assert!(params.verify(&keys.y, b"Goodbye, world", &sig));
+    }
+}
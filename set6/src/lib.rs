@@ -0,0 +1,5 @@
+pub mod bleichenbacher;
+pub mod dsa;
+pub mod parity;
+pub mod pkcs1;
+pub mod unpadded;
@@ -0,0 +1,102 @@
+use std::cell::Cell;
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::Zero;
+use set5::rsa::{KeyPair, PublicKey};
+
+/*
+ * RSA parity oracle
+ *
+ * Multiplying the ciphertext by 2^e doubles the plaintext mod N. N is odd, so
+ * 2m mod N is even exactly when 2m didn't wrap, i.e. when m < N/2. Every query
+ * halves the interval the plaintext can be in, so log2(N) of them pin it down.
+ *
+ * The bounds are kept as exact fractions of N: rounding them to integers along
+ * the way throws away the last few bits and garbles the final byte.
+ */
+
+pub struct ParityOracle {
+    keys: KeyPair,
+    queries: Cell<usize>,
+}
+
+impl ParityOracle {
+    pub fn new(keys: KeyPair) -> Self {
+        ParityOracle { keys, queries: Cell::new(0) }
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.keys.public
+    }
+
+    pub fn is_even(&self, c: &BigUint) -> bool {
+        self.queries.set(self.queries.get() + 1);
+        self.keys.private.decrypt(c).is_even()
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+fn to_biguint(x: BigInt) -> BigUint {
+    x.to_biguint().unwrap()
+}
+
+// `progress` sees the upper bound after every query, watch it close in on the plaintext
+pub fn decrypt<F: FnMut(&BigUint)>(oracle: &ParityOracle, c: &BigUint, mut progress: F) -> BigUint {
+    let PublicKey { e, n } = oracle.public().clone();
+    let doubler = BigUint::from(2u32).modpow(&e, &n);
+    let two = BigRational::from_integer(BigInt::from(2));
+    let mut c = c.clone();
+    let mut lower = BigRational::zero();
+    let mut upper = BigRational::from_integer(BigInt::from(n.clone()));
+
+    for _ in 0..n.bits() {
+        c = c * &doubler % &n;
+        let mid = (&lower + &upper) / &two;
+        if oracle.is_even(&c) {
+            upper = mid;
+        } else {
+            lower = mid;
+        }
+        progress(&to_biguint(upper.floor().to_integer()));
+    }
+    // The interval is now narrower than 1 and the plaintext sits strictly inside
+    // it (or on 0), so rounding the lower bound up is exact
+    to_biguint(lower.ceil().to_integer())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::rsa::{generate, int_to_bytes};
+
+    #[test]
+    fn challenge46_parity_oracle() {
+        let oracle = ParityOracle::new(generate(768, 65537, &mut StdRng::seed_from_u64(46)));
+        let message = b"That's why I found you don't play around with the Funky Cold Medina";
+        let c = oracle.public().encrypt_bytes(message);
+
+        let mut bounds = vec![];
+        let recovered = decrypt(&oracle, &c, |upper| bounds.push(upper.clone()));
+        assert_eq!(int_to_bytes(&recovered, 0), message.to_vec());
+        assert_eq!(oracle.queries(), 768);
+        assert_eq!(bounds.len(), 768);
+        assert!(bounds.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn challenge46_edge_plaintexts() {
+        let oracle = ParityOracle::new(generate(256, 65537, &mut StdRng::seed_from_u64(46)));
+        let n = oracle.public().n.clone();
+        for m in [BigUint::zero(), BigUint::from(1u32), &n >> 1, &n - 1u32] {
+            let c = oracle.public().encrypt(&m);
+            assert_eq!(decrypt(&oracle, &c, |_| ()), m);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use num_bigint::BigUint;
+use rand::Rng;
+use set5::numtheory::iroot;
+use set5::rsa::{bytes_to_int, int_to_bytes, PrivateKey, PublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/*
+ * PKCS#1 v1.5 signatures (EMSA-PKCS1-v1_5)
+ *
+ * 00 01 FF .. FF 00 DigestInfo HASH, padded with FF up to the size of the
+ * modulus, then signed with raw RSA. DigestInfo is the ASN.1 blob naming the
+ * hash function, which we carry around pre-encoded.
+ */
+
+const SHA1_DIGEST_INFO: &[u8] = &[
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+];
+const SHA256_DIGEST_INFO: &[u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+// PKCS#1 insists on at least eight bytes of FF
+const MIN_PADDING: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn digest_info(self) -> &'static [u8] {
+        match self {
+            HashAlgorithm::Sha1 => SHA1_DIGEST_INFO,
+            HashAlgorithm::Sha256 => SHA256_DIGEST_INFO,
+        }
+    }
+
+    pub fn hash(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha1 => Sha1::digest(message).to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(message).to_vec(),
+        }
+    }
+
+    // DigestInfo followed by the hash of the message
+    fn digest(self, message: &[u8]) -> Vec<u8> {
+        let mut digest = self.digest_info().to_vec();
+        digest.extend(self.hash(message));
+        digest
+    }
+}
+
+pub fn encode_signature(hash: HashAlgorithm, message: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    let digest = hash.digest(message);
+    if len < digest.len() + MIN_PADDING + 3 {
+        return Err(String::from("modulus too short"));
+    }
+    let mut em = vec![0x00, 0x01];
+    em.extend(vec![0xff; len - digest.len() - 3]);
+    em.push(0x00);
+    em.extend(digest);
+    Ok(em)
+}
+
+pub fn sign(private: &PrivateKey, hash: HashAlgorithm, message: &[u8]) -> Result<Vec<u8>, String> {
+    let len = private.n.bits().div_ceil(8) as usize;
+    let em = encode_signature(hash, message, len)?;
+    Ok(int_to_bytes(&private.decrypt(&bytes_to_int(&em)), len))
+}
+
+fn open(public: &PublicKey, signature: &[u8]) -> Vec<u8> {
+    int_to_bytes(&public.encrypt(&bytes_to_int(signature)), public.size())
+}
+
+// Rebuilds the whole encoding and compares, so there's nowhere to hide garbage
+pub fn verify(public: &PublicKey, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+    let s = bytes_to_int(signature);
+    if signature.len() != public.size() || s >= public.n {
+        return false;
+    }
+    encode_signature(hash, message, public.size()).is_ok_and(|em| open(public, signature) == em)
+}
+
+// Walks the encoding left to right and stops as soon as it has read a hash,
+// never checking that the hash actually ends at the end of the block
+pub fn verify_sloppy(public: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    let em = open(public, signature);
+    if em.len() < 3 || em[0] != 0x00 || em[1] != 0x01 || em[2] != 0xff {
+        return false;
+    }
+    let rest = match em[2..].iter().position(|&b| b != 0xff) {
+        Some(i) if em[2 + i] == 0x00 => &em[3 + i..],
+        _ => return false,
+    };
+    [HashAlgorithm::Sha1, HashAlgorithm::Sha256].iter()
+        .find(|hash| rest.starts_with(hash.digest_info()))
+        .is_some_and(|&hash| rest.starts_with(&hash.digest(message)))
+}
+
+/*
+ * Bleichenbacher's e=3 forgery
+ *
+ * The sloppy verifier is happy with 00 01 FF 00 DigestInfo HASH followed by
+ * anything at all. Fill that "anything" with FF and take the integer cube root:
+ * cubing it back lands below the maximum, and as long as the garbage spans more
+ * than the ~2/3 of the block the rounding can eat into, it still starts with
+ * our prefix. No private key needed.
+ */
+pub fn forge(public: &PublicKey, hash: HashAlgorithm, message: &[u8]) -> Option<Vec<u8>> {
+    if public.e != BigUint::from(3u32) {
+        return None;
+    }
+    let len = public.size();
+    let mut prefix = vec![0x00, 0x01, 0xff, 0x00];
+    prefix.extend(hash.digest(message));
+    if prefix.len() >= len {
+        return None;
+    }
+
+    let garbage = len - prefix.len();
+    let mut lowest = prefix.clone();
+    lowest.extend(vec![0x00; garbage]);
+    let mut highest = prefix;
+    highest.extend(vec![0xff; garbage]);
+
+    let root = iroot(&bytes_to_int(&highest), 3);
+    if root.pow(3) < bytes_to_int(&lowest) {
+        return None;
+    }
+    Some(int_to_bytes(&root, len))
+}
+
+/*
+ * PKCS#1 v1.5 encryption padding (type 2)
+ *
+ * 00 02 PS 00 M, where PS is at least eight random non-zero bytes filling the
+ * block out to the size of the modulus.
+ */
+pub fn pad_encryption<R: Rng>(message: &[u8], len: usize, rng: &mut R) -> Result<Vec<u8>, String> {
+    if len < message.len() + MIN_PADDING + 3 {
+        return Err(String::from("message too long"));
+    }
+    let mut em = vec![0x00, 0x02];
+    em.extend((0..len - message.len() - 3).map(|_| rng.gen_range(1, 256) as u8));
+    em.push(0x00);
+    em.extend_from_slice(message);
+    Ok(em)
+}
+
+pub fn unpad_encryption(em: &[u8]) -> Result<Vec<u8>, String> {
+    if em.len() < MIN_PADDING + 3 || em[0] != 0x00 || em[1] != 0x02 {
+        return Err(String::from("invalid padding"));
+    }
+    match em[2..].iter().position(|&b| b == 0x00) {
+        Some(i) if i >= MIN_PADDING => Ok(em[3 + i..].to_vec()),
+        _ => Err(String::from("invalid padding")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::rsa::generate;
+
+    const MESSAGE: &[u8] = b"hi mom";
+
+    #[test]
+    fn encoding_layout() {
+        let em = encode_signature(HashAlgorithm::Sha1, MESSAGE, 128).unwrap();
+        assert_eq!(em.len(), 128);
+        assert_eq!(&em[..3], &[0x00, 0x01, 0xff]);
+        assert_eq!(em[128 - 36], 0x00);
+        assert!(em.ends_with(&Sha1::digest(MESSAGE)));
+        assert!(encode_signature(HashAlgorithm::Sha256, MESSAGE, 60).is_err());
+    }
+
+    #[test]
+    fn encryption_padding() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let em = pad_encryption(b"kick it, CC", 32, &mut rng).unwrap();
+        assert_eq!(em.len(), 32);
+        assert_eq!(&em[..2], &[0x00, 0x02]);
+        assert!(em[2..20].iter().all(|&b| b != 0x00));
+        assert_eq!(unpad_encryption(&em).unwrap(), b"kick it, CC");
+        assert!(pad_encryption(&[0x41; 22], 32, &mut rng).is_err());
+
+        // the padding string has to be at least eight bytes long
+        assert!(unpad_encryption(b"\x00\x02\x01\x01\x01\x01\x01\x01\x01\x00hi").is_err());
+        assert!(unpad_encryption(b"\x00\x01\x01\x01\x01\x01\x01\x01\x01\x01\x00hi").is_err());
+    }
+
+    #[test]
+    fn challenge42_sign_and_verify() {
+        let keys = generate(1024, 3, &mut StdRng::seed_from_u64(42));
+        for &hash in &[HashAlgorithm::Sha1, HashAlgorithm::Sha256] {
+            let signature = sign(&keys.private, hash, MESSAGE).unwrap();
+            assert!(verify(&keys.public, hash, MESSAGE, &signature));
+            assert!(verify_sloppy(&keys.public, MESSAGE, &signature));
+            assert!(!verify(&keys.public, hash, b"hi dad", &signature));
+            assert!(!verify_sloppy(&keys.public, b"hi dad", &signature));
+        }
+    }
+
+    #[test]
+    fn challenge42_e3_forgery() {
+        let keys = generate(1024, 3, &mut StdRng::seed_from_u64(42));
+        let forged = forge(&keys.public, HashAlgorithm::Sha1, MESSAGE).unwrap();
+        assert!(verify_sloppy(&keys.public, MESSAGE, &forged));
+        assert!(!verify(&keys.public, HashAlgorithm::Sha1, MESSAGE, &forged));
+
+        // SHA-256's longer digest leaves too little garbage for a 1024 bit modulus
+        assert!(forge(&keys.public, HashAlgorithm::Sha256, MESSAGE).is_none());
+    }
+
+    #[test]
+    fn challenge42_e3_forgery_sha256() {
+        let keys = generate(1536, 3, &mut StdRng::seed_from_u64(42));
+        let forged = forge(&keys.public, HashAlgorithm::Sha256, MESSAGE).unwrap();
+        assert!(verify_sloppy(&keys.public, MESSAGE, &forged));
+        assert!(!verify(&keys.public, HashAlgorithm::Sha256, MESSAGE, &forged));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::Rng;
+use set5::numtheory::invmod;
+use set5::rsa::{KeyPair, PublicKey};
+use sha2::{Digest, Sha256};
+
+/*
+ * Unpadded message recovery oracle
+ *
+ * The server will decrypt anything once, remembering a hash of every
+ * ciphertext it has seen so the same blob can't be replayed. Textbook RSA is
+ * multiplicative though: (s^e C)^d = s P mod N, so blinding the ciphertext
+ * with a random s gets it past the replay check and dividing s back out gives
+ * up the original plaintext.
+ */
+
+pub struct DecryptionServer {
+    keys: KeyPair,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl DecryptionServer {
+    pub fn new(keys: KeyPair) -> Self {
+        DecryptionServer { keys, seen: HashSet::new() }
+    }
+
+    pub fn public(&self) -> &PublicKey {
+        &self.keys.public
+    }
+
+    pub fn decrypt(&mut self, c: &BigUint) -> Result<BigUint, String> {
+        if !self.seen.insert(Sha256::digest(&c.to_bytes_be()).to_vec()) {
+            return Err(String::from("ciphertext already decrypted"));
+        }
+        Ok(self.keys.private.decrypt(c))
+    }
+}
+
+pub fn recover<R: Rng>(server: &mut DecryptionServer, c: &BigUint, rng: &mut R) -> Result<BigUint, String> {
+    let PublicKey { e, n } = server.public().clone();
+    // s has to be invertible mod N to be divided back out, which a random s almost always is
+    let (s, s_inverse) = loop {
+        let s = rng.gen_biguint_range(&BigUint::from(2u32), &n);
+        if let Some(s_inverse) = invmod(&s, &n) {
+            break (s, s_inverse);
+        }
+    };
+    let blinded = (s.modpow(&e, &n) * c) % &n;
+    let p = server.decrypt(&blinded)?;
+    Ok((p * s_inverse) % &n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::rsa::{generate, int_to_bytes};
+
+    #[test]
+    fn challenge41_replay_rejected() {
+        let mut server = DecryptionServer::new(generate(512, 65537, &mut StdRng::seed_from_u64(41)));
+        let c = server.public().encrypt_bytes(b"{time: 1356304276, social: '555-55-5555'}");
+        assert!(server.decrypt(&c).is_ok());
+        assert!(server.decrypt(&c).is_err());
+    }
+
+    #[test]
+    fn challenge41_unpadded_message_recovery() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let mut server = DecryptionServer::new(generate(512, 65537, &mut rng));
+        let message = b"{time: 1356304276, social: '555-55-5555'}";
+        let c = server.public().encrypt_bytes(message);
+        server.decrypt(&c).unwrap();
+
+        let recovered = recover(&mut server, &c, &mut rng).unwrap();
+        assert_eq!(int_to_bytes(&recovered, 0), message.to_vec());
+    }
+}
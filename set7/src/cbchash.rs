@@ -0,0 +1,105 @@
+use set2::{cbc_encrypt, ecb_decrypt, pkcs7_pad, xor};
+
+use crate::cbcmac::cbc_mac;
+
+const BLOCK_SIZE: usize = 16;
+pub const HASH_KEY: &[u8] = b"YELLOW SUBMARINE";
+
+/*
+ * CBC-MAC as a hash function
+ *
+ * With the key public, the cipher runs backwards as easily as forwards. A
+ * message that ends on a block boundary gets a whole block of padding, so the
+ * last two blocks through the cipher are our glue block and [16; 16]:
+ *   hash = E(E(chain(pad(prefix)) ^ glue) ^ [16; 16])
+ * Decrypt back from the target hash through the padding block and XOR in the
+ * chaining value our padded prefix leaves behind, and that's the glue:
+ *   glue = D(D(hash) ^ [16; 16]) ^ chain(pad(prefix))
+ */
+
+pub fn cbc_hash(message: &[u8]) -> Vec<u8> {
+    cbc_mac(message, &[0; BLOCK_SIZE], HASH_KEY)
+}
+
+// The chaining value after running CBC over already block-aligned `data`
+fn chain(data: &[u8]) -> Vec<u8> {
+    let ciphertext = cbc_encrypt(data, &[0; BLOCK_SIZE], HASH_KEY);
+    ciphertext[ciphertext.len() - BLOCK_SIZE..].to_vec()
+}
+
+// A message starting with `prefix` that hashes to `target_hash`, or None if
+// that isn't a hash at all. Everything after the prefix is its padding and the
+// glue block, so a prefix ending in a JavaScript `//` comment hides the lot as
+// long as none of it is a line break: spaces are added to the prefix until
+// that comes out clean.
+pub fn forge_collision(prefix: &[u8], target_hash: &[u8]) -> Option<Vec<u8>> {
+    if target_hash.len() != BLOCK_SIZE {
+        return None;
+    }
+    let last = xor(&ecb_decrypt(target_hash, HASH_KEY), &[BLOCK_SIZE as u8; BLOCK_SIZE]);
+    let inner = ecb_decrypt(&last, HASH_KEY);
+    let mut prefix = prefix.to_vec();
+    loop {
+        let mut forged = pkcs7_pad(&prefix, BLOCK_SIZE as u32);
+        forged.extend(xor(&inner, &chain(&forged)));
+        if !forged[prefix.len()..].iter().any(|&b| b == b'\n' || b == b'\r') {
+            return Some(forged);
+        }
+        prefix.push(b' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &[u8] = b"alert('MZA who was that?');\n";
+    const PREFIX: &[u8] = b"alert('Ayo, the Wu is back!');//";
+
+    #[test]
+    fn challenge50_known_hash() {
+        assert_eq!(hex::encode(cbc_hash(ORIGINAL)), "296b8d7cb78a243dda4d0a61d33bbdd1");
+    }
+
+    // Standard PKCS#7, written out here rather than borrowed from cbcmac
+    fn pkcs7_hash(message: &[u8]) -> Vec<u8> {
+        let n = BLOCK_SIZE - message.len() % BLOCK_SIZE;
+        let padded = [message, &vec![n as u8; n]].concat();
+        chain(&padded)
+    }
+
+    #[test]
+    fn challenge50_hash_collision() {
+        let target = cbc_hash(ORIGINAL);
+        let forged = forge_collision(PREFIX, &target).unwrap();
+        assert_eq!(cbc_hash(&forged), target);
+        assert!(forged.starts_with(PREFIX));
+        // The comment runs to the end, with no line break to cut it short
+        assert!(!forged.iter().any(|&b| b == b'\n' || b == b'\r'));
+    }
+
+    #[test]
+    fn challenge50_forgery_under_pkcs7() {
+        let forged = forge_collision(PREFIX, &cbc_hash(ORIGINAL)).unwrap();
+        assert_eq!(hex::encode(pkcs7_hash(ORIGINAL)), "296b8d7cb78a243dda4d0a61d33bbdd1");
+        assert_eq!(pkcs7_hash(&forged), pkcs7_hash(ORIGINAL));
+    }
+
+    #[test]
+    fn challenge50_collisions_for_other_prefixes() {
+        let targets = [cbc_hash(ORIGINAL), cbc_hash(b""), vec![0xff; BLOCK_SIZE]];
+        for prefix in &[&b""[..], b"//", b"alert(1);//", b"console.log('exactly sixteen');//"] {
+            for target in &targets {
+                let forged = forge_collision(prefix, target).unwrap();
+                assert_eq!(&cbc_hash(&forged), target);
+                assert!(forged.starts_with(prefix));
+            }
+        }
+    }
+
+    #[test]
+    fn not_a_hash() {
+        assert_eq!(forge_collision(PREFIX, b"short"), None);
+        assert_eq!(forge_collision(PREFIX, &[0; 17]), None);
+    }
+}
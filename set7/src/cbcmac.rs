@@ -0,0 +1,237 @@
+use set2::{cbc_encrypt, generate_rand, pkcs7_pad, xor};
+use subtle::ConstantTimeEq;
+
+const BLOCK_SIZE: usize = 16;
+
+/*
+ * CBC-MAC
+ *
+ * CBC-encrypt the message and keep only the last block. That's a fine MAC
+ * for fixed-length messages under a fixed IV, and the two bank protocols
+ * below each break one of those conditions.
+ */
+
+pub fn cbc_mac(message: &[u8], iv: &[u8], key: &[u8]) -> Vec<u8> {
+    let ciphertext = cbc_encrypt(&pkcs7_pad(message, BLOCK_SIZE as u32), iv, key);
+    ciphertext[ciphertext.len() - BLOCK_SIZE..].to_vec()
+}
+
+// Constant time, same as the SRP proofs in set5
+pub fn verify(message: &[u8], iv: &[u8], key: &[u8], mac: &[u8]) -> bool {
+    cbc_mac(message, iv, key).ct_eq(mac).into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub from: u32,
+    pub to: u32,
+    pub amount: u64,
+}
+
+fn number<T: std::str::FromStr>(bs: &[u8]) -> Option<T> {
+    std::str::from_utf8(bs).ok()?.parse().ok()
+}
+
+// The value of `name=` up to the next '&'
+fn param<'a>(message: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    message.split(|&b| b == b'&')
+        .find_map(|kv| kv.strip_prefix(name.as_bytes())?.strip_prefix(b"="))
+}
+
+fn split_request(request: &[u8], trailer: usize) -> Result<(&[u8], &[u8]), String> {
+    if request.len() < trailer {
+        return Err(String::from("request too short"));
+    }
+    Ok(request.split_at(request.len() - trailer))
+}
+
+/*
+ * The bank's API server shares a key with the client. Requests come in two
+ * flavours:
+ *
+ * v1  message || IV || MAC, with message = from=#{from}&to=#{to}&amount=#{amount}
+ * v2  message || MAC under a zero IV, with
+ *     message = from=#{from}&tx_list=#{to}:#{amount}(;#{to}:#{amount})*
+ *
+ * Transfers that verify are appended to the ledger.
+ */
+pub struct Server {
+    key: Vec<u8>,
+    pub ledger: Vec<Transfer>,
+}
+
+impl Server {
+    pub fn new(key: &[u8]) -> Self {
+        Server { key: key.to_vec(), ledger: vec![] }
+    }
+
+    pub fn handle_v1(&mut self, request: &[u8]) -> Result<Transfer, String> {
+        let (rest, mac) = split_request(request, BLOCK_SIZE)?;
+        let (message, iv) = split_request(rest, BLOCK_SIZE)?;
+        if !verify(message, iv, &self.key, mac) {
+            return Err(String::from("bad mac"));
+        }
+        let transfer = Transfer {
+            from: param(message, "from").and_then(number).ok_or("missing from")?,
+            to: param(message, "to").and_then(number).ok_or("missing to")?,
+            amount: param(message, "amount").and_then(number).ok_or("missing amount")?,
+        };
+        self.ledger.push(transfer.clone());
+        Ok(transfer)
+    }
+
+    // Liberal in what it accepts: tx_list runs to the end of the message and
+    // entries that don't parse are skipped
+    pub fn handle_v2(&mut self, request: &[u8]) -> Result<Vec<Transfer>, String> {
+        let (message, mac) = split_request(request, BLOCK_SIZE)?;
+        if !verify(message, &[0; BLOCK_SIZE], &self.key, mac) {
+            return Err(String::from("bad mac"));
+        }
+        let from = param(message, "from").and_then(number).ok_or("missing from")?;
+        let start = message.windows(9).position(|w| w == b"&tx_list=").ok_or("missing tx_list")? + 9;
+        let transfers = message[start..].split(|&b| b == b';')
+            .filter_map(|tx| {
+                let mut parts = tx.splitn(2, |&b| b == b':');
+                let to = number(parts.next()?)?;
+                let amount = number(parts.next()?)?;
+                Some(Transfer { from, to, amount })
+            })
+            .collect::<Vec<_>>();
+        self.ledger.extend(transfers.iter().cloned());
+        Ok(transfers)
+    }
+}
+
+// The client will only ever sign transfers out of its own account
+pub struct Client {
+    key: Vec<u8>,
+    pub account: u32,
+}
+
+impl Client {
+    pub fn new(key: &[u8], account: u32) -> Self {
+        Client { key: key.to_vec(), account }
+    }
+
+    pub fn transfer_v1(&self, to: u32, amount: u64) -> Vec<u8> {
+        let iv = generate_rand(BLOCK_SIZE);
+        let mut request = format!("from={}&to={}&amount={}", self.account, to, amount).into_bytes();
+        let mac = cbc_mac(&request, &iv, &self.key);
+        request.extend(iv);
+        request.extend(mac);
+        request
+    }
+
+    pub fn transfer_v2(&self, transfers: &[(u32, u64)]) -> Vec<u8> {
+        let tx_list = transfers.iter()
+            .map(|(to, amount)| format!("{}:{}", to, amount))
+            .collect::<Vec<_>>()
+            .join(";");
+        let mut request = format!("from={}&tx_list={}", self.account, tx_list).into_bytes();
+        let mac = cbc_mac(&request, &[0; BLOCK_SIZE], &self.key);
+        request.extend(mac);
+        request
+    }
+}
+
+/*
+ * v1: the IV is only ever XORed into the first plaintext block, so any change
+ * to that block can be cancelled out by the same change to the IV. Sign a
+ * transfer from our own account and rewrite the sender.
+ */
+pub fn forge_v1(attacker: &Client, victim: u32, amount: u64) -> Option<Vec<u8>> {
+    let request = attacker.transfer_v1(attacker.account, amount);
+    let (rest, mac) = request.split_at(request.len() - BLOCK_SIZE);
+    let (message, iv) = rest.split_at(rest.len() - BLOCK_SIZE);
+
+    let forged = format!("from={}&to={}&amount={}", victim, attacker.account, amount).into_bytes();
+    // The rewrite has to stay within the first block and keep everything after it in place
+    if forged.len() != message.len() || forged[BLOCK_SIZE..] != message[BLOCK_SIZE..] {
+        return None;
+    }
+    let forged_iv = xor(&xor(iv, &message[..BLOCK_SIZE]), &forged[..BLOCK_SIZE]);
+
+    let mut forged_request = forged;
+    forged_request.extend(forged_iv);
+    forged_request.extend_from_slice(mac);
+    Some(forged_request)
+}
+
+/*
+ * v2: with a fixed IV, the MAC of one message is exactly the chaining value
+ * going into the next. Appending (our first block ^ victim's MAC) || the rest
+ * of our message to the victim's padded message reproduces our message's
+ * chain, and so our MAC. Our padding carries over too, since the forgery is
+ * as long as our message mod 16. The glue block turns into garbage in the
+ * tx_list, which the server skips over on its way to our transfer at the end.
+ */
+pub fn forge_v2(captured: &[u8], attacker: &Client, amount: u64) -> Vec<u8> {
+    let (victim_message, victim_mac) = captured.split_at(captured.len() - BLOCK_SIZE);
+    let ours = attacker.transfer_v2(&[(attacker.account, 1), (attacker.account, amount)]);
+    let (our_message, our_mac) = ours.split_at(ours.len() - BLOCK_SIZE);
+
+    let mut forged = pkcs7_pad(victim_message, BLOCK_SIZE as u32);
+    forged.extend(xor(&our_message[..BLOCK_SIZE], victim_mac));
+    forged.extend_from_slice(&our_message[BLOCK_SIZE..]);
+    forged.extend_from_slice(our_mac);
+    forged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VICTIM: u32 = 2;
+    const ATTACKER: u32 = 3;
+    const MILLION: u64 = 1_000_000;
+
+    #[test]
+    fn padding_is_part_of_the_mac() {
+        let key = generate_rand(16);
+        let iv = [0; BLOCK_SIZE];
+        for message in &[&b"from=2&tx_list=5:100"[..], b"YELLOW SUBMARINE", b""] {
+            let padded = pkcs7_pad(message, BLOCK_SIZE as u32);
+            assert_ne!(cbc_mac(message, &iv, &key), cbc_mac(&padded, &iv, &key));
+        }
+    }
+
+    #[test]
+    fn challenge49_honest_transfers() {
+        let key = generate_rand(16);
+        let mut server = Server::new(&key);
+        let client = Client::new(&key, VICTIM);
+        assert_eq!(server.handle_v1(&client.transfer_v1(5, 10)).unwrap(), Transfer { from: VICTIM, to: 5, amount: 10 });
+        assert_eq!(server.handle_v2(&client.transfer_v2(&[(5, 10), (6, 20)])).unwrap(),
+                   vec![Transfer { from: VICTIM, to: 5, amount: 10 }, Transfer { from: VICTIM, to: 6, amount: 20 }]);
+
+        let mut tampered = client.transfer_v1(5, 10);
+        tampered[0] ^= 1;
+        assert!(server.handle_v1(&tampered).is_err());
+        assert!(server.handle_v1(b"short").is_err());
+        assert_eq!(server.ledger.len(), 3);
+    }
+
+    #[test]
+    fn challenge49_iv_forgery() {
+        let key = generate_rand(16);
+        let mut server = Server::new(&key);
+        let attacker = Client::new(&key, ATTACKER);
+        let forged = forge_v1(&attacker, VICTIM, MILLION).unwrap();
+        assert_eq!(server.handle_v1(&forged).unwrap(), Transfer { from: VICTIM, to: ATTACKER, amount: MILLION });
+
+        // account numbers of different lengths push the change past the first block
+        assert!(forge_v1(&attacker, 1234567, MILLION).is_none());
+    }
+
+    #[test]
+    fn challenge49_length_extension_forgery() {
+        let key = generate_rand(16);
+        let mut server = Server::new(&key);
+        let captured = Client::new(&key, VICTIM).transfer_v2(&[(5, 100), (6, 250)]);
+        let attacker = Client::new(&key, ATTACKER);
+
+        let transfers = server.handle_v2(&forge_v2(&captured, &attacker, MILLION)).unwrap();
+        assert_eq!(transfers.first(), Some(&Transfer { from: VICTIM, to: 5, amount: 100 }));
+        assert_eq!(transfers.last(), Some(&Transfer { from: VICTIM, to: ATTACKER, amount: MILLION }));
+    }
+}
@@ -0,0 +1,219 @@
+use std::cell::Cell;
+use std::convert::TryInto;
+use std::io::Write;
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use set2::{cbc_encrypt, generate_rand, pkcs7_pad};
+use set3::ctr_apply;
+
+const BLOCK_SIZE: usize = 16;
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/*
+ * Compression ratio side channel (CRIME)
+ *
+ * The request gets compressed before it's encrypted, so whatever we put in
+ * the body compresses better when it repeats part of the cookie. Encryption
+ * hides the content but not the length.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Ctr,
+    Cbc,
+}
+
+pub fn format_request(session_id: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut request = format!(
+        "POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid={}\nContent-Length: {}\n",
+        String::from_utf8_lossy(session_id), body.len()).into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+// Every query is encrypted under a fresh key (and nonce or IV), so only the
+// length carries over between queries
+pub struct Oracle {
+    mode: Mode,
+    session_id: Vec<u8>,
+    queries: Cell<usize>,
+}
+
+impl Oracle {
+    pub fn new(mode: Mode, session_id: &[u8]) -> Self {
+        Oracle { mode, session_id: session_id.to_vec(), queries: Cell::new(0) }
+    }
+
+    pub fn query(&self, body: &[u8]) -> usize {
+        self.queries.set(self.queries.get() + 1);
+        let compressed = compress(&format_request(&self.session_id, body));
+        let key = generate_rand(BLOCK_SIZE);
+        match self.mode {
+            Mode::Ctr => {
+                let nonce = u64::from_le_bytes(generate_rand(8)[..].try_into().unwrap());
+                ctr_apply(&compressed, nonce, &key).len()
+            }
+            Mode::Cbc => {
+                let iv = generate_rand(BLOCK_SIZE);
+                cbc_encrypt(&pkcs7_pad(&compressed, BLOCK_SIZE as u32), &iv, &key).len()
+            }
+        }
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+// Filler that can't show up in the cookie. Its content moves the compressed
+// stream around relative to byte boundaries, and its length relative to block
+// boundaries. Random bytes would do that too, but too many of them and the
+// compressor gives up and stores the whole request as is.
+fn filler(n: usize) -> Vec<u8> {
+    const SYMBOLS: &[u8] = b"!#$%&()*,-.:;<>?@";
+    generate_rand(n).into_iter().map(|b| SYMBOLS[b as usize % SYMBOLS.len()]).collect()
+}
+
+fn query_guess(oracle: &Oracle, pad: &[u8], known: &[u8], guess: u8) -> usize {
+    let mut body = pad.to_vec();
+    body.extend_from_slice(known);
+    body.push(guess);
+    oracle.query(&body)
+}
+
+// One round: which guesses come out longer than the shortest behind the same
+// filler. For CBC the filler is first cut down to where the pivot just spills
+// into another block, so anything that compresses a byte smaller stays behind.
+//
+// There's always at least a block of filler: with less, there are a few body
+// lengths where the right guess never comes out ahead.
+fn losers(oracle: &Oracle, mode: Mode, known: &[u8], guesses: &[u8], pivot: u8) -> Vec<bool> {
+    let mut pad = match mode {
+        Mode::Ctr => filler(BLOCK_SIZE),
+        Mode::Cbc => filler(4 * BLOCK_SIZE),
+    };
+    if mode == Mode::Cbc {
+        let full = query_guess(oracle, &pad, known, pivot);
+        while pad.len() > BLOCK_SIZE && query_guess(oracle, &pad[1..], known, pivot) == full {
+            pad.remove(0);
+        }
+    }
+    let lengths = guesses.iter()
+        .map(|&c| query_guess(oracle, &pad, known, c))
+        .collect::<Vec<_>>();
+    let min = *lengths.iter().min().unwrap();
+    lengths.into_iter().map(|len| len > min).collect()
+}
+
+// DEFLATE codes match lengths in groups (11-12, 13-14, ..., 19-22, ...) with
+// extra bits to pick within the group
+fn same_length_code(len: usize) -> bool {
+    const GROUP_STARTS: &[usize] = &[11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227];
+    (11..257).contains(&len) && !GROUP_STARTS.contains(&(len + 1))
+}
+
+/*
+ * Guess the cookie one character at a time, sending the tail of what we know
+ * before the guess so a right guess extends an existing match instead of
+ * costing a literal. The tail is sized so the longer match falls in the same
+ * length code as the shorter one: otherwise the right guess can pull a fresh
+ * symbol into the Huffman tables and cost more than the literal it saved.
+ *
+ * Even then we only save a few bits, and the length we see is rounded to a
+ * byte (or a whole block for CBC), so most rounds tie. Random filler in front
+ * shifts where the rounding happens. Keep going until one guess has lost
+ * `MARGIN` fewer rounds than the rest, in case some round goes the wrong way.
+ *
+ * CBC only tells us which guesses fall on which side of a block boundary, so
+ * each round lines the boundary up just under a guess that isn't in the lead.
+ * Lining it up under the leader would learn nothing when the leader is right,
+ * and would only give the odd wrong guess that comes out smaller a chance to
+ * knock it back.
+ *
+ * The cookie ends when a newline wins.
+ */
+pub fn recover_session_id(oracle: &Oracle, mode: Mode) -> Option<Vec<u8>> {
+    const PREFIX: &[u8] = b"Cookie: sessionid=";
+    const MARGIN: usize = 3;
+    const MAX_ROUNDS: usize = 512;
+    let mut alphabet = BASE64.to_vec();
+    alphabet.push(b'\n');
+
+    let mut known = PREFIX.to_vec();
+    loop {
+        let tail = (11..=known.len()).rev().find(|&len| same_length_code(len))?;
+        let context = &known[known.len() - tail..];
+        let mut losses = vec![0; alphabet.len()];
+        let mut alive = alphabet.clone();
+        for _ in 0..MAX_ROUNDS {
+            let best = *losses.iter().min().unwrap();
+            alive = alphabet.iter().zip(&losses)
+                .filter(|&(_, &lost)| lost < best + MARGIN)
+                .map(|(&c, _)| c)
+                .collect();
+            if alive.len() == 1 {
+                break;
+            }
+            let trailing = alphabet.iter().zip(&losses)
+                .filter(|&(_, &lost)| lost > best && lost < best + MARGIN)
+                .map(|(&c, _)| c)
+                .collect::<Vec<_>>();
+            let pool = if trailing.is_empty() { &alive } else { &trailing };
+            let pivot = pool[generate_rand(1)[0] as usize % pool.len()];
+            let lost = losers(oracle, mode, context, &alive, pivot);
+            for (c, lost) in alive.iter().zip(lost) {
+                if lost {
+                    losses[alphabet.iter().position(|a| a == c).unwrap()] += 1;
+                }
+            }
+        }
+        match alive[..] {
+            [b'\n'] => return Some(known.split_off(PREFIX.len())),
+            [c] => known.push(c),
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION_ID: &[u8] = b"TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+    #[test]
+    fn challenge51_compression_shrinks_repeats() {
+        let oracle = Oracle::new(Mode::Ctr, SESSION_ID);
+        assert!(oracle.query(b"sessionid=TmV2") < oracle.query(b"sessionid=XXXX"));
+    }
+
+    // Real PKCS#7: a block-aligned stream still gets a whole block of padding
+    #[test]
+    fn challenge51_cbc_always_pads() {
+        let oracle = Oracle::new(Mode::Cbc, SESSION_ID);
+        for n in 0..64 {
+            let body = filler(n);
+            let compressed = compress(&format_request(SESSION_ID, &body)).len();
+            assert_eq!(oracle.query(&body), (compressed / BLOCK_SIZE + 1) * BLOCK_SIZE);
+        }
+    }
+
+    #[test]
+    fn challenge51_ctr() {
+        let oracle = Oracle::new(Mode::Ctr, SESSION_ID);
+        assert_eq!(recover_session_id(&oracle, Mode::Ctr).unwrap(), SESSION_ID);
+    }
+
+    #[test]
+    fn challenge51_cbc() {
+        let oracle = Oracle::new(Mode::Cbc, SESSION_ID);
+        assert_eq!(recover_session_id(&oracle, Mode::Cbc).unwrap(), SESSION_ID);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::md::{blocks, cross_collision, Compress, MerkleDamgard, BLOCK_SIZE};
+
+/*
+ * Kelsey-Schneier second preimages
+ *
+ * MD strengthening means a second preimage has to be the same length as the
+ * original, so we can't just collide with some intermediate state and chop
+ * the message there. An expandable message gets around that: k pieces, each
+ * either one block or 2^i + 1 blocks that end in the same state, which chain
+ * together into a message of any length from k to k + 2^k - 1 blocks. From
+ * its final state find a bridge block into any of the target's 2^k
+ * intermediate states, and pick the expansion that puts the bridge at the
+ * right place.
+ */
+
+pub struct ExpandableMessage {
+    // piece i is (one block, 2^i + 1 blocks)
+    pub pieces: Vec<(Vec<u8>, Vec<u8>)>,
+    pub state: Vec<u8>,
+}
+
+impl ExpandableMessage {
+    pub fn new<C: Compress>(md: &MerkleDamgard<C>, state: &[u8], k: usize) -> Self {
+        let mut state = state.to_vec();
+        let mut pieces = vec![];
+        for i in 0..k {
+            let dummy = vec![0; BLOCK_SIZE << i];
+            let (short, last, next) = cross_collision(md, &state, &md.iterate(&state, &dummy));
+            pieces.push((short, [dummy, last].concat()));
+            state = next;
+        }
+        ExpandableMessage { pieces, state }
+    }
+
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1 << self.pieces.len()) - 1
+    }
+
+    // Piece i goes in long exactly when bit i of the extra length is set
+    pub fn expand(&self, blocks: usize) -> Option<Vec<u8>> {
+        if blocks < self.min_blocks() || blocks > self.max_blocks() {
+            return None;
+        }
+        let extra = blocks - self.min_blocks();
+        Some(self.pieces.iter().enumerate()
+            .flat_map(|(i, (short, long))| if extra >> i & 1 == 0 { short } else { long })
+            .cloned()
+            .collect())
+    }
+}
+
+// The state after each whole block of `message`, keyed by how many blocks in
+pub fn intermediate_states<C: Compress>(md: &MerkleDamgard<C>, message: &[u8]) -> Vec<Vec<u8>> {
+    message.chunks_exact(BLOCK_SIZE)
+        .scan(md.iv().to_vec(), |state, block| {
+            *state = md.compress(state, block);
+            Some(state.clone())
+        })
+        .collect()
+}
+
+// A different message of the same length and hash. Takes about
+// k·2^(b/2+1) + 2^k calls to build the expandable message and 2^(b-k) to find
+// the bridge, against 2^b for brute force.
+pub fn second_preimage<C: Compress>(md: &MerkleDamgard<C>, message: &[u8]) -> Option<Vec<u8>> {
+    let n = message.len() / BLOCK_SIZE;
+    if n < 2 {
+        return None;
+    }
+    let k = (0..).take_while(|&k| 1 << k <= n).last().unwrap();
+    let expandable = ExpandableMessage::new(md, md.iv(), k);
+
+    // only states the expandable message can reach with the bridge as the next block
+    let targets = intermediate_states(md, message).into_iter().enumerate()
+        .map(|(i, state)| (state, i + 1))
+        .filter(|&(_, blocks)| blocks > expandable.min_blocks() && blocks <= expandable.max_blocks() + 1)
+        .collect::<HashMap<_, _>>();
+    let (bridge, blocks) = blocks()
+        .find_map(|block| targets.get(&md.compress(&expandable.state, &block)).map(|&blocks| (block, blocks)))?;
+
+    let mut forged = expandable.expand(blocks - 1)?;
+    forged.extend(bridge);
+    forged.extend_from_slice(&message[blocks * BLOCK_SIZE..]);
+    Some(forged)
+}
+
+// For comparison: vary the first block until it lands on the original's
+// first intermediate state
+pub fn brute_force_second_preimage<C: Compress>(md: &MerkleDamgard<C>, message: &[u8]) -> Option<Vec<u8>> {
+    let (first, rest) = message.split_at(BLOCK_SIZE.min(message.len()));
+    if first.len() < BLOCK_SIZE {
+        return None;
+    }
+    let target = md.compress(md.iv(), first);
+    let block = blocks().find(|block| block != first && md.compress(md.iv(), block) == target)?;
+    Some([&block[..], rest].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use set2::generate_rand;
+
+    use super::*;
+    use crate::md::toy_hash;
+
+    fn long_message(blocks: usize) -> Vec<u8> {
+        // a partial block at the end to make sure the tail carries over
+        generate_rand(blocks * BLOCK_SIZE + 5)
+    }
+
+    #[test]
+    fn challenge53_expandable_message() {
+        let md = toy_hash(16);
+        let expandable = ExpandableMessage::new(&md, md.iv(), 5);
+        assert_eq!((expandable.min_blocks(), expandable.max_blocks()), (5, 36));
+        for blocks in 5..=36 {
+            let message = expandable.expand(blocks).unwrap();
+            assert_eq!(message.len(), blocks * BLOCK_SIZE);
+            assert_eq!(md.iterate(md.iv(), &message), expandable.state);
+        }
+        assert!(expandable.expand(4).is_none());
+        assert!(expandable.expand(37).is_none());
+    }
+
+    #[test]
+    fn challenge53_second_preimage() {
+        for &bits in &[16, 24] {
+            let md = toy_hash(bits);
+            let message = long_message(1 << 10);
+            let forged = second_preimage(&md, &message).unwrap();
+            assert_ne!(forged, message);
+            assert_eq!(forged.len(), message.len());
+            assert_eq!(md.hash(&forged), md.hash(&message));
+        }
+    }
+
+    #[test]
+    fn challenge53_beats_brute_force() {
+        let md = toy_hash(24);
+        let message = long_message(1 << 12);
+        md.reset_calls();
+        second_preimage(&md, &message).unwrap();
+        // brute force expects 2^24
+        assert!(md.calls() < 1 << 20);
+    }
+
+    // cargo test --release challenge53_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn challenge53_benchmark() {
+        for &bits in &[16, 24, 32] {
+            let md = toy_hash(bits);
+            let message = long_message(1 << 14);
+            md.reset_calls();
+            let start = Instant::now();
+            let forged = second_preimage(&md, &message).unwrap();
+            println!("{}-bit expandable message: {} calls in {:?}", bits, md.calls(), start.elapsed());
+            assert_eq!(md.hash(&forged), md.hash(&message));
+
+            // 2^32 calls is a bit much to sit through
+            if bits <= 24 {
+                md.reset_calls();
+                let start = Instant::now();
+                let forged = brute_force_second_preimage(&md, &message).unwrap();
+                println!("{}-bit brute force: {} calls in {:?}", bits, md.calls(), start.elapsed());
+                assert_eq!(md.hash(&forged), md.hash(&message));
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod cbchash;
+pub mod cbcmac;
+pub mod compression;
+pub mod expandable;
+pub mod md;
+pub mod md4;
+pub mod nostradamus;
+pub mod rc4;
+pub mod wang;
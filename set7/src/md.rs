@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use aes::block_cipher_trait::generic_array::GenericArray;
+use aes::block_cipher_trait::BlockCipher;
+use aes::Aes128;
+use set2::generate_rand;
+
+pub const BLOCK_SIZE: usize = 16;
+const TOY_IV: &[u8] = &[0x67, 0x45, 0x23, 0x01];
+
+/*
+ * Merkle-Damgård
+ *
+ * Chop the padded message into blocks and fold a compression function over
+ * them, starting from a fixed IV. The state is the hash. Padding appends 0x80,
+ * zeros, and the message length in bits (MD strengthening), so messages of
+ * different lengths never share a last block.
+ */
+
+pub trait Compress {
+    fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> Vec<u8>> Compress for F {
+    fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        self(state, block)
+    }
+}
+
+// Encrypt the block under the state (zero-padded out to a key), keeping as
+// many bytes of output as there were in the state
+pub struct TruncatedAes;
+
+impl Compress for TruncatedAes {
+    fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        let mut key = [0; 16];
+        key[..state.len()].copy_from_slice(state);
+        let cipher = Aes128::new(GenericArray::from_slice(&key));
+        let mut output = GenericArray::clone_from_slice(block);
+        cipher.encrypt_block(&mut output);
+        output[..state.len()].to_vec()
+    }
+}
+
+// The bytes that pad a message of `len` bytes out to a whole number of blocks
+pub fn padding(len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80];
+    padding.resize((BLOCK_SIZE * 2 - 8 - (len + 1) % BLOCK_SIZE) % BLOCK_SIZE + 1, 0);
+    padding.extend_from_slice(&((len as u64) * 8).to_be_bytes());
+    padding
+}
+
+pub fn pad(message: &[u8]) -> Vec<u8> {
+    [message, &padding(message.len())].concat()
+}
+
+// Counts every call to the compression function, which is what all the
+// attacks below are priced in
+pub struct MerkleDamgard<C> {
+    compress: C,
+    iv: Vec<u8>,
+    calls: AtomicU64,
+}
+
+impl<C: Compress> MerkleDamgard<C> {
+    pub fn new(compress: C, iv: &[u8]) -> Self {
+        MerkleDamgard { compress, iv: iv.to_vec(), calls: AtomicU64::new(0) }
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    pub fn state_size(&self) -> usize {
+        self.iv.len()
+    }
+
+    pub fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.compress.compress(state, block)
+    }
+
+    // Run already block-aligned data through from `state`, no padding
+    pub fn iterate(&self, state: &[u8], blocks: &[u8]) -> Vec<u8> {
+        blocks.chunks(BLOCK_SIZE).fold(state.to_vec(), |state, block| self.compress(&state, block))
+    }
+
+    pub fn hash(&self, message: &[u8]) -> Vec<u8> {
+        self.iterate(&self.iv, &pad(message))
+    }
+
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_calls(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+    }
+}
+
+// A `bits`-wide hash out of truncated AES, small enough to collide at will
+pub fn toy_hash(bits: usize) -> MerkleDamgard<TruncatedAes> {
+    assert!(bits % 8 == 0 && bits / 8 <= TOY_IV.len(), "toy hashes are 8 to 32 bits wide");
+    MerkleDamgard::new(TruncatedAes, &TOY_IV[..bits / 8])
+}
+
+/*
+ * Joux multicollisions
+ *
+ * A birthday search finds a pair of blocks that collide from a given state in
+ * about 2^(b/2) calls. Do it again from the resulting state, and again: n
+ * pairs chained together pick out 2^n messages that all end in the same state,
+ * for only n times the cost of one collision.
+ */
+
+// Fresh blocks that won't repeat within one search
+pub fn blocks() -> impl Iterator<Item = Vec<u8>> {
+    let salt = generate_rand(BLOCK_SIZE - 8);
+    (0u64..).map(move |i| [&salt[..], &i.to_le_bytes()].concat())
+}
+
+// A block from `a` and a block from `b` that land in the same state, found by
+// growing a table on each side until they meet
+pub fn cross_collision<C: Compress>(md: &MerkleDamgard<C>, a: &[u8], b: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let mut from_a = HashMap::new();
+    let mut from_b = HashMap::new();
+    for (x, y) in blocks().zip(blocks()) {
+        let next = md.compress(a, &x);
+        if let Some(y) = from_b.get(&next) {
+            return (x, Vec::clone(y), next);
+        }
+        from_a.insert(next, x);
+
+        let next = md.compress(b, &y);
+        if let Some(x) = from_a.get(&next) {
+            return (Vec::clone(x), y, next);
+        }
+        from_b.insert(next, y);
+    }
+    unreachable!()
+}
+
+// Two different blocks taking `state` to the same place, and that place
+pub fn block_collision<C: Compress>(md: &MerkleDamgard<C>, state: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let mut seen = HashMap::new();
+    blocks()
+        .find_map(|block| {
+            let next = md.compress(state, &block);
+            seen.insert(next.clone(), block.clone()).map(|other| (other, block, next))
+        })
+        .unwrap()
+}
+
+#[derive(Debug, Clone)]
+pub struct Multicollision {
+    pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    pub state: Vec<u8>,
+}
+
+impl Multicollision {
+    pub fn new(state: &[u8]) -> Self {
+        Multicollision { pairs: vec![], state: state.to_vec() }
+    }
+
+    // Double the number of colliding messages
+    pub fn extend<C: Compress>(&mut self, md: &MerkleDamgard<C>) {
+        let (a, b, next) = block_collision(md, &self.state);
+        self.pairs.push((a, b));
+        self.state = next;
+    }
+
+    // Bit i of `choice` picks which block of the ith pair goes in
+    pub fn message(&self, choice: u64) -> Vec<u8> {
+        self.pairs.iter().enumerate()
+            .flat_map(|(i, (a, b))| if choice >> i & 1 == 0 { a } else { b })
+            .cloned()
+            .collect()
+    }
+
+    pub fn messages(&self) -> Vec<Vec<u8>> {
+        (0..1u64 << self.pairs.len()).map(|choice| self.message(choice)).collect()
+    }
+}
+
+pub fn multicollision<C: Compress>(md: &MerkleDamgard<C>, n: usize) -> Multicollision {
+    let mut collision = Multicollision::new(md.iv());
+    for _ in 0..n {
+        collision.extend(md);
+    }
+    collision
+}
+
+// Walk every message in the multicollision through `g`, sharing prefixes, and
+// look for two that end in the same state
+fn find_in_multicollision<C: Compress>(g: &MerkleDamgard<C>, collision: &Multicollision) -> Option<(u64, u64)> {
+    fn walk<C: Compress>(g: &MerkleDamgard<C>, pairs: &[(Vec<u8>, Vec<u8>)], depth: usize, state: Vec<u8>,
+                         choice: u64, seen: &mut HashMap<Vec<u8>, u64>) -> Option<(u64, u64)> {
+        if depth == pairs.len() {
+            return seen.insert(state, choice).map(|other| (other, choice));
+        }
+        let (a, b) = &pairs[depth];
+        walk(g, pairs, depth + 1, g.compress(&state, a), choice, seen)
+            .or_else(|| walk(g, pairs, depth + 1, g.compress(&state, b), choice | 1 << depth, seen))
+    }
+    walk(g, &collision.pairs, 0, g.iv().to_vec(), 0, &mut HashMap::new())
+}
+
+/*
+ * Cascading a cheap b1-bit hash f with an expensive b2-bit hash g doesn't buy
+ * a (b1 + b2)-bit hash. Build a 2^(b2/2) multicollision in f, at a cost of
+ * (b2/2)·2^(b1/2), and one pair in there probably collides in g as well. If
+ * not, add another pair to the multicollision and look again.
+ */
+pub fn cascade_collision<F: Compress, G: Compress>(f: &MerkleDamgard<F>, g: &MerkleDamgard<G>) -> (Vec<u8>, Vec<u8>) {
+    let mut collision = multicollision(f, g.state_size() * 8 / 2);
+    loop {
+        if let Some((x, y)) = find_in_multicollision(g, &collision) {
+            return (collision.message(x), collision.message(y));
+        }
+        collision.extend(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge52_padding() {
+        for len in 0..64 {
+            let padded = pad(&vec![0; len]);
+            assert_eq!(padded.len() % BLOCK_SIZE, 0);
+            assert!(padded.len() >= len + 9 && padded.len() < len + 9 + BLOCK_SIZE);
+            assert_eq!(padded[len], 0x80);
+        }
+    }
+
+    #[test]
+    fn challenge52_toy_hashes() {
+        for &bits in &[16, 24, 32] {
+            let md = toy_hash(bits);
+            assert_eq!(md.hash(b"YELLOW SUBMARINE").len(), bits / 8);
+            assert_ne!(md.hash(b"YELLOW SUBMARINE"), md.hash(b"YELLOW SUBMARINF"));
+            // two blocks apiece
+            assert_eq!(md.calls(), 6);
+        }
+    }
+
+    #[test]
+    fn challenge52_joux_multicollision() {
+        let f = toy_hash(16);
+        let collision = multicollision(&f, 4);
+        // about 2^8 calls per pair
+        assert!(f.calls() < 4 * 4 * (1 << 8));
+
+        let messages = collision.messages();
+        assert_eq!(messages.len(), 16);
+        let hash = f.hash(&messages[0]);
+        for (i, m) in messages.iter().enumerate() {
+            assert_eq!(f.hash(m), hash);
+            assert!(messages[..i].iter().all(|other| other != m));
+        }
+    }
+
+    #[test]
+    fn challenge52_cascade_collision() {
+        let f = toy_hash(16);
+        let g = toy_hash(32);
+        let (x, y) = cascade_collision(&f, &g);
+        assert_ne!(x, y);
+        // nowhere near the 2^24 a birthday attack on the 48-bit cascade would take
+        assert!(f.calls() + g.calls() < 1 << 20);
+
+        assert_eq!(f.hash(&x), f.hash(&y));
+        assert_eq!(g.hash(&x), g.hash(&y));
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * MD4 (RFC 1320)
+ *
+ * Three rounds of sixteen steps over little-endian words. The round functions
+ * are exposed for the collision attack, which needs to run the steps one at a
+ * time.
+ */
+
+pub const IV: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+pub const BLOCK_SIZE: usize = 64;
+
+pub const K2: u32 = 0x5a827999;
+pub const K3: u32 = 0x6ed9eba1;
+pub const SHIFTS1: [u32; 4] = [3, 7, 11, 19];
+pub const SHIFTS2: [u32; 4] = [3, 5, 9, 13];
+pub const SHIFTS3: [u32; 4] = [3, 9, 11, 15];
+pub const ORDER2: [usize; 16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+pub const ORDER3: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+pub fn words(block: &[u8]) -> [u32; 16] {
+    let mut words = [0; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+pub fn bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes().to_vec()).collect()
+}
+
+pub fn compress(state: &[u32; 4], m: &[u32; 16]) -> [u32; 4] {
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..48 {
+        let (mix, k, s) = match i / 16 {
+            0 => (f(b, c, d), m[i], SHIFTS1[i % 4]),
+            1 => (g(b, c, d).wrapping_add(K2), m[ORDER2[i % 16]], SHIFTS2[i % 4]),
+            _ => (h(b, c, d).wrapping_add(K3), m[ORDER3[i % 16]], SHIFTS3[i % 4]),
+        };
+        let next = a.wrapping_add(mix).wrapping_add(k).rotate_left(s);
+        // rotate the registers so `a` is always the one being replaced
+        a = d;
+        d = c;
+        c = b;
+        b = next;
+    }
+    [
+        state[0].wrapping_add(a),
+        state[1].wrapping_add(b),
+        state[2].wrapping_add(c),
+        state[3].wrapping_add(d),
+    ]
+}
+
+pub fn pad(message: &[u8]) -> Vec<u8> {
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64) * 8).to_le_bytes());
+    padded
+}
+
+pub fn md4(message: &[u8]) -> Vec<u8> {
+    let state = pad(message).chunks_exact(BLOCK_SIZE)
+        .fold(IV, |state, block| compress(&state, &words(block)));
+    bytes(&state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge55_md4_test_vectors() {
+        let vectors: &[(&[u8], &str)] = &[
+            (b"", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", "d9130a8164549fe818874806e1c7014b"),
+            (b"abcdefghijklmnopqrstuvwxyz", "d79e1c308aa5bbcdeea8ed63df412da9"),
+            (b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+             "e33b4ddc9c38f2199c3e7b164fcc0536"),
+        ];
+        for (message, digest) in vectors {
+            assert_eq!(hex::encode(md4(message)), *digest);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+use set2::generate_rand;
+
+use crate::md::{blocks, cross_collision, padding, Compress, MerkleDamgard, BLOCK_SIZE};
+
+/*
+ * Nostradamus: herding with a diamond structure
+ *
+ * Start from 2^k arbitrary states and pair them off, finding a block for each
+ * side of a pair that takes both to the same state. That halves the number of
+ * states; keep going until there's one left. Every leaf now has a path of k
+ * blocks to the root, so we can publish the hash of a message ending there
+ * before knowing what comes first. Once the prefix is known, one glue block
+ * from the state after it into any leaf, about 2^(b-k) tries, finishes the
+ * job.
+ *
+ * MD strengthening pins down the length of the message, so the diamond is
+ * built for a prefix of a given number of blocks.
+ */
+
+pub struct Diamond {
+    // levels[l][i] is a state and the block taking it up to levels[l + 1][i / 2]
+    pub levels: Vec<Vec<(Vec<u8>, Vec<u8>)>>,
+    pub root: Vec<u8>,
+    pub prefix_blocks: usize,
+}
+
+impl Diamond {
+    // The pairs on each level are independent, so they're collided in parallel
+    pub fn build<C: Compress + Sync>(md: &MerkleDamgard<C>, depth: usize, prefix_blocks: usize) -> Self {
+        assert!(depth < md.state_size() * 8, "more leaves than there are states");
+        let mut leaves = HashSet::new();
+        while leaves.len() < 1 << depth {
+            leaves.insert(generate_rand(md.state_size()));
+        }
+
+        let mut states = leaves.into_iter().collect::<Vec<_>>();
+        let mut levels = vec![];
+        while states.len() > 1 {
+            let collisions = states.par_chunks(2)
+                .map(|pair| cross_collision(md, &pair[0], &pair[1]))
+                .collect::<Vec<_>>();
+            let level = states.chunks(2).zip(&collisions)
+                .flat_map(|(pair, (a, b, _))| vec![(pair[0].clone(), a.clone()), (pair[1].clone(), b.clone())])
+                .collect();
+            levels.push(level);
+            states = collisions.into_iter().map(|(_, _, next)| next).collect();
+        }
+        Diamond { levels, root: states.remove(0), prefix_blocks }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Prefix, glue block, path to the root
+    pub fn message_len(&self) -> usize {
+        (self.prefix_blocks + 1 + self.depth()) * BLOCK_SIZE
+    }
+
+    // The hash to publish ahead of time
+    pub fn commitment<C: Compress>(&self, md: &MerkleDamgard<C>) -> Vec<u8> {
+        md.iterate(&self.root, &padding(self.message_len()))
+    }
+
+    fn path(&self, mut leaf: usize) -> Vec<u8> {
+        let mut path = vec![];
+        for level in &self.levels {
+            path.extend_from_slice(&level[leaf].1);
+            leaf /= 2;
+        }
+        path
+    }
+
+    // The prefix is padded out with spaces to the size the diamond was built for
+    pub fn forge<C: Compress>(&self, md: &MerkleDamgard<C>, prefix: &[u8]) -> Result<Vec<u8>, String> {
+        let size = self.prefix_blocks * BLOCK_SIZE;
+        if prefix.len() > size {
+            return Err(format!("prefix longer than {} bytes", size));
+        }
+        let mut message = prefix.to_vec();
+        message.resize(size, b' ');
+
+        let leaves = self.levels[0].iter().enumerate()
+            .map(|(i, (state, _))| (state, i))
+            .collect::<HashMap<_, _>>();
+        let state = md.iterate(md.iv(), &message);
+        let (glue, leaf) = blocks()
+            .find_map(|block| leaves.get(&md.compress(&state, &block)).map(|&leaf| (block, leaf)))
+            .unwrap();
+        message.extend(glue);
+        message.extend(self.path(leaf));
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md::toy_hash;
+
+    const RESULTS: &[u8] = b"2020 World Series: Los Angeles Dodgers 4, Tampa Bay Rays 2. \
+                             2021 World Series: Atlanta Braves 4, Houston Astros 2.";
+
+    #[test]
+    fn challenge54_diamond_paths() {
+        let md = toy_hash(16);
+        let diamond = Diamond::build(&md, 4, 1);
+        assert_eq!(diamond.levels.iter().map(Vec::len).collect::<Vec<_>>(), vec![16, 8, 4, 2]);
+        for (leaf, (state, _)) in diamond.levels[0].iter().enumerate() {
+            assert_eq!(md.iterate(state, &diamond.path(leaf)), diamond.root);
+        }
+    }
+
+    #[test]
+    fn challenge54_nostradamus() {
+        let md = toy_hash(16);
+        let diamond = Diamond::build(&md, 8, 8);
+        let commitment = diamond.commitment(&md);
+
+        // the season happens
+        let forged = diamond.forge(&md, RESULTS).unwrap();
+        assert!(forged.starts_with(RESULTS));
+        assert_eq!(forged.len(), diamond.message_len());
+        assert_eq!(md.hash(&forged), commitment);
+
+        let other = diamond.forge(&md, b"Mets win").unwrap();
+        assert_eq!(md.hash(&other), commitment);
+        assert!(diamond.forge(&md, &[b'!'; 200]).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+
+use set2::generate_rand;
+
+use crate::md4::{bytes, compress, f, g, words, IV, K2, ORDER2, SHIFTS1, SHIFTS2};
+
+/*
+ * Wang's MD4 collisions
+ *
+ * M' = M + Δ with Δm1 = 2^31, Δm2 = 2^31 - 2^28, Δm12 = -2^16 cancels out
+ * through all three rounds, provided a long list of conditions holds on the
+ * bits of the intermediate state. Rather than search for M at random we steer
+ * it into the conditions:
+ *
+ * Round 1: each step uses one message word, so compute the step, force the
+ *   conditions onto the result, and solve the step for the word that gives it.
+ * Round 2: a5, d5, c5 and b5 reuse m0, m4, m8 and m12. To flip a bad bit
+ *   of one of them, move its word by the right power of two, either directly
+ *   or by changing round 1's output at an earlier step so that the word
+ *   solved for it moves. Then solve the next round 1 words again so that
+ *   everything after the change stays as it was. Round 1 bits tied to the
+ *   changed bit are flipped along with it. A change only stays if round 1
+ *   still holds and round 2 gets closer.
+ *
+ * a5 always comes out right and d5 almost always. Most c5 and b5 bits do
+ *   too, but c5 bits 25 and 28 run into round 1 conditions on most routes and
+ *   stay wrong about one time in five. The conditions from a6 on, and the
+ *   rest of rounds 2 and 3, are left to chance. A few thousand tries covers it.
+ *
+ * Bits below are numbered from 0, where the paper counts from 1.
+ */
+
+#[derive(Debug, Clone, Copy)]
+enum Cond {
+    Zero,
+    One,
+    // equal to the same bit of the state this many steps back
+    Eq(usize),
+}
+
+use Cond::*;
+
+type Conditions = &'static [(u32, Cond)];
+
+const ROUND1: [Conditions; 16] = [
+    /* a1 */ &[(6, Eq(1))],
+    /* d1 */ &[(6, Zero), (7, Eq(1)), (10, Eq(1))],
+    /* c1 */ &[(6, One), (7, One), (10, Zero), (25, Eq(1))],
+    /* b1 */ &[(6, One), (7, Zero), (10, Zero), (25, Zero)],
+    /* a2 */ &[(7, One), (10, One), (13, Eq(1)), (25, Zero)],
+    /* d2 */ &[(13, Zero), (18, Eq(1)), (19, Eq(1)), (20, Eq(1)), (21, Eq(1)), (25, One)],
+    /* c2 */ &[(12, Eq(1)), (13, Zero), (14, Eq(1)), (18, Zero), (19, Zero), (20, One), (21, Zero)],
+    /* b2 */ &[(12, One), (13, One), (14, Zero), (16, Eq(1)), (18, Zero), (19, Zero), (20, Zero), (21, Zero)],
+    /* a3 */ &[(12, One), (13, One), (14, One), (16, Zero), (18, Zero), (19, Zero), (20, Zero), (21, One),
+               (22, Eq(1)), (25, Eq(1))],
+    /* d3 */ &[(12, One), (13, One), (14, One), (16, Zero), (19, Zero), (20, One), (21, One), (22, Zero),
+               (25, One), (29, Eq(1))],
+    /* c3 */ &[(16, One), (19, Zero), (20, Zero), (21, Zero), (22, Zero), (25, Zero), (29, One), (31, Eq(1))],
+    /* b3 */ &[(19, Zero), (20, One), (21, One), (22, Eq(1)), (25, One), (29, Zero), (31, Zero)],
+    /* a4 */ &[(22, Zero), (25, Zero), (26, Eq(1)), (28, Eq(1)), (29, One), (31, Zero)],
+    /* d4 */ &[(22, Zero), (25, Zero), (26, One), (28, One), (29, Zero), (31, One)],
+    /* c4 */ &[(18, Eq(1)), (22, One), (25, One), (26, Zero), (28, Zero), (29, Zero)],
+    /* b4 */ &[(18, Zero), (25, One), (26, One), (28, One), (29, Zero)],
+];
+
+const A5: Conditions = &[(18, Eq(2)), (25, One), (26, Zero), (28, One), (31, One)];
+const D5: Conditions = &[(18, Eq(1)), (25, Eq(2)), (26, Eq(2)), (28, Eq(2)), (31, Eq(2))];
+const C5: Conditions = &[(25, Eq(1)), (26, Eq(1)), (28, Eq(1)), (29, Eq(1)), (31, Eq(1))];
+const B5: Conditions = &[(28, Eq(1)), (29, One), (31, Zero)];
+const ROUND2: [Conditions; 4] = [A5, D5, C5, B5];
+
+// The state runs a0 d0 c0 b0 a1 d1 c1 b1 ..., so q[i + 4] is the output of
+// step i and the registers feeding it are q[i..i + 4]
+fn initial_state() -> Vec<u32> {
+    vec![IV[0], IV[3], IV[2], IV[1]]
+}
+
+fn step1(q: &[u32], i: usize, m: u32) -> u32 {
+    q[i].wrapping_add(f(q[i + 3], q[i + 2], q[i + 1])).wrapping_add(m).rotate_left(SHIFTS1[i % 4])
+}
+
+// The message word that makes step i come out as q[i + 4]
+fn solve1(q: &[u32], i: usize) -> u32 {
+    q[i + 4].rotate_right(SHIFTS1[i % 4]).wrapping_sub(q[i]).wrapping_sub(f(q[i + 3], q[i + 2], q[i + 1]))
+}
+
+fn step2(q: &[u32], j: usize, m: &[u32; 16]) -> u32 {
+    let i = j + 16;
+    q[i].wrapping_add(g(q[i + 3], q[i + 2], q[i + 1]))
+        .wrapping_add(m[ORDER2[j]])
+        .wrapping_add(K2)
+        .rotate_left(SHIFTS2[j % 4])
+}
+
+fn wanted(q: &[u32], i: usize, bit: u32, cond: Cond) -> u32 {
+    match cond {
+        Zero => 0,
+        One => 1,
+        Eq(back) => q[i - back] >> bit & 1,
+    }
+}
+
+// Force the conditions on a new value for q[i]
+fn force(q: &[u32], i: usize, mut value: u32, conditions: Conditions) -> u32 {
+    for &(bit, cond) in conditions {
+        value = value & !(1 << bit) | wanted(q, i, bit, cond) << bit;
+    }
+    value
+}
+
+fn violated(q: &[u32], i: usize, conditions: Conditions) -> Vec<u32> {
+    conditions.iter()
+        .filter(|&&(bit, cond)| q[i] >> bit & 1 != wanted(q, i, bit, cond))
+        .map(|&(bit, _)| bit)
+        .collect()
+}
+
+// Give round 1's outputs at some steps new values and re-solve the words from
+// the first of them to four past the last, so every other round 1 output
+// stays put. Only the bits that changed can break round 1 conditions, either
+// their own or ones tied to them, so the caller has to check.
+fn nudge(q: &mut [u32], m: &mut [u32; 16], values: &[(usize, u32)]) {
+    for &(step, value) in values {
+        q[step + 4] = value;
+    }
+    let first = values.iter().map(|&(step, _)| step).min().unwrap();
+    let last = values.iter().map(|&(step, _)| step).max().unwrap();
+    for (i, word) in m.iter_mut().enumerate().take(last + 5).skip(first) {
+        *word = solve1(q, i);
+    }
+}
+
+// Flip `bit` of round 1's output at `step`, along with the same bit of any
+// step tied to it by an Eq condition, so those still hold
+fn flip(q: &[u32], step: usize, bit: u32) -> Vec<(usize, u32)> {
+    let tied = |i: usize| ROUND1[i].iter().any(|&(b, cond)| b == bit && matches!(cond, Eq(1)));
+    let mut first = step;
+    while first > 0 && tied(first) {
+        first -= 1;
+    }
+    let mut last = step;
+    while last + 1 < ROUND1.len() && tied(last + 1) {
+        last += 1;
+    }
+    (first..=last).map(|i| (i, q[i + 4] ^ 1 << bit)).collect()
+}
+
+// The round 1 nudges that flip `bit` of round 2 step j, which takes the word
+// round 1 used at step `word`. That word has to move by 2^k, one way or the
+// other:
+//   - add or subtract it, whichever flips round 2's bit without a carry, and
+//     redo step `word`, whose output may carry in round 1 instead
+//   - flip the bit of step `word`'s output it rotates into, which keeps round
+//     1 clean but may carry in round 2
+//   - flip bit k of whichever input the step's F picks there, or of the input
+//     doing the picking if the other two differ. Either moves the word and
+//     leaves the output alone.
+// This is the paper's multi-step modification, more or less: a flip drags
+// along whatever it's tied to and the words after it are solved again.
+fn nudges(q: &[u32], m: &[u32; 16], j: usize, bit: u32) -> Vec<Vec<(usize, u32)>> {
+    let word = ORDER2[j];
+    let k = (bit + 32 - SHIFTS2[j % 4]) % 32;
+    let delta = if q[j + 20] >> bit & 1 == 0 { 1 << k } else { (1u32 << k).wrapping_neg() };
+    let mut nudges = vec![
+        vec![(word, step1(q, word, m[word].wrapping_add(delta)))],
+        flip(q, word, (k + SHIFTS1[word % 4]) % 32),
+    ];
+    if word >= 3 {
+        let (x, y, z) = (q[word + 3] >> k & 1, q[word + 2] >> k & 1, q[word + 1] >> k & 1);
+        nudges.push(if x == 1 { flip(q, word - 2, k) } else { flip(q, word - 3, k) });
+        if y != z {
+            nudges.push(flip(q, word - 1, k));
+        }
+    }
+    nudges
+}
+
+fn round2_violations(q: &[u32], steps: usize) -> Vec<usize> {
+    (0..steps).map(|j| violated(q, j + 20, ROUND2[j]).len()).collect()
+}
+
+// Returns the state after round 1
+fn massage_round1(m: &mut [u32; 16]) -> Vec<u32> {
+    let mut q = initial_state();
+    for (i, conditions) in ROUND1.iter().enumerate() {
+        let value = step1(&q, i, m[i]);
+        q.push(0);
+        q[i + 4] = force(&q, i + 4, value, conditions);
+        m[i] = solve1(&q, i);
+    }
+    q
+}
+
+fn round1_holds(q: &[u32]) -> bool {
+    ROUND1.iter().enumerate().all(|(i, conditions)| violated(q, i + 4, conditions).is_empty())
+}
+
+// Try each nudge for each violated bit in turn. A nudge only stays if round 1
+// still holds, no earlier round 2 step got worse and this one got better.
+fn massage_round2(q: &mut Vec<u32>, m: &mut [u32; 16]) {
+    for (j, conditions) in ROUND2.iter().enumerate() {
+        q.push(step2(q, j, m));
+        for bit in violated(q, j + 20, conditions) {
+            for values in nudges(q, m, j, bit) {
+                let (before_q, before_m) = (q.clone(), *m);
+                let before = round2_violations(q, j + 1);
+                nudge(q, m, &values);
+                for i in 0..=j {
+                    q[i + 20] = step2(q, i, m);
+                }
+                let after = round2_violations(q, j + 1);
+                if round1_holds(q) && after[j] < before[j] && (0..j).all(|i| after[i] <= before[i]) {
+                    break;
+                }
+                *q = before_q;
+                *m = before_m;
+            }
+        }
+    }
+}
+
+pub fn massage(m: &mut [u32; 16]) {
+    let mut q = massage_round1(m);
+    massage_round2(&mut q, m);
+}
+
+pub fn differential(m: &[u32; 16]) -> [u32; 16] {
+    let mut m_prime = *m;
+    m_prime[1] = m[1].wrapping_add(1 << 31);
+    m_prime[2] = m[2].wrapping_add((1 << 31) - (1 << 28));
+    m_prime[12] = m[12].wrapping_sub(1 << 16);
+    m_prime
+}
+
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub m: Vec<u8>,
+    pub m_prime: Vec<u8>,
+    pub attempts: u64,
+    pub elapsed: Duration,
+}
+
+pub fn find_collision() -> Collision {
+    let start = Instant::now();
+    (1..)
+        .find_map(|attempts| {
+            let mut m = words(&generate_rand(64));
+            massage(&mut m);
+            let m_prime = differential(&m);
+            if compress(&IV, &m) == compress(&IV, &m_prime) {
+                Some(Collision { m: bytes(&m), m_prime: bytes(&m_prime), attempts, elapsed: start.elapsed() })
+            } else {
+                None
+            }
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md4::md4;
+
+    fn run(m: &[u32; 16], steps: usize) -> Vec<u32> {
+        let mut q = initial_state();
+        for (i, &word) in m.iter().enumerate() {
+            q.push(step1(&q, i, word));
+        }
+        for j in 0..steps - 16 {
+            q.push(step2(&q, j, m));
+        }
+        q
+    }
+
+    #[test]
+    fn challenge55_message_modification() {
+        const RUNS: usize = 1000;
+        let mut wrong = ROUND2.iter().map(|conditions| vec![0; conditions.len()]).collect::<Vec<_>>();
+        for _ in 0..RUNS {
+            let mut m = words(&generate_rand(64));
+            massage_round1(&mut m);
+            let q = run(&m, 16);
+            for (i, conditions) in ROUND1.iter().enumerate() {
+                assert!(violated(&q, i + 4, conditions).is_empty(), "step {}", i);
+            }
+
+            massage(&mut m);
+            let q = run(&m, 20);
+            assert!(round1_holds(&q));
+            assert!(violated(&q, 20, A5).is_empty());
+            for (j, conditions) in ROUND2.iter().enumerate() {
+                for bit in violated(&q, j + 20, conditions) {
+                    wrong[j][conditions.iter().position(|&(b, _)| b == bit).unwrap()] += 1;
+                }
+            }
+        }
+        // Left alone, each of these is wrong half the time
+        for (j, counts) in wrong.iter().enumerate() {
+            assert!(counts.iter().all(|&n| n < RUNS / 4), "step {}: {:?}", j + 16, counts);
+        }
+    }
+
+    #[test]
+    fn challenge55_md4_collision() {
+        let collision = find_collision();
+        println!("MD4 collision after {} attempts in {:?}", collision.attempts, collision.elapsed);
+        assert_ne!(collision.m, collision.m_prime);
+        assert_eq!(md4(&collision.m), md4(&collision.m_prime));
+    }
+}
@@ -0,0 +1,128 @@
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+use rand::Rng;
+use set5::numtheory::invmod;
+
+use crate::ec::{Params, Point};
+use crate::ecdsa::{hash_message, sign_with_nonce, Signature};
+use crate::lll::{lll, ratio, Vector};
+
+/*
+ * ECDSA with biased nonces
+ *
+ * If the low l bits of every nonce are zero then k = 2^l b for some
+ * b < n/2^l, and from s = (H(m) + dr)/k
+ *   b = d r/(s 2^l) + H(m)/(s 2^l)
+ * so with t = r/(s 2^l) and u = H(m)/(-s 2^l) (both mod n), dt - u is small
+ * mod n for every signature. That's the hidden number problem, and d hides in
+ * a short vector of the lattice with rows
+ *   n e_i  for each signature
+ *   (t_1, ..., t_k, 2^-l, 0)
+ *   (u_1, ..., u_k, 0, n/2^l)
+ * namely d times the t row, minus the u row, plus whatever multiples of n
+ * bring each dt_i - u_i down to b_i: (b_1, ..., b_k, d/2^l, -n/2^l). Every
+ * entry is below n/2^l, and with enough signatures LLL finds it.
+ */
+
+pub const BIASED_BITS: u32 = 8;
+
+// Signs with a nonce whose low `bits` bits are all zero
+pub fn sign_biased<R: Rng>(params: &Params, d: &BigUint, message: &[u8], bits: u32, rng: &mut R) -> Signature {
+    let h = hash_message(message, &params.n);
+    let top = &params.n >> bits as usize;
+    loop {
+        let k = rng.gen_biguint_range(&BigUint::one(), &top) << bits as usize;
+        if let Some(sig) = sign_with_nonce(params, d, &h, &k) {
+            return sig;
+        }
+    }
+}
+
+fn rational(x: &BigUint) -> BigRational {
+    BigRational::from_integer(BigInt::from(x.clone()))
+}
+
+pub fn hnp_basis(params: &Params, signed: &[(Vec<u8>, Signature)], bits: u32) -> Option<Vec<Vector>> {
+    let n = &params.n;
+    let size = signed.len() + 2;
+    let mut t_row = vec![BigRational::zero(); size];
+    let mut u_row = vec![BigRational::zero(); size];
+    let mut basis = vec![];
+    for (i, (message, sig)) in signed.iter().enumerate() {
+        let mut row = vec![BigRational::zero(); size];
+        row[i] = rational(n);
+        basis.push(row);
+        // 1 / (s 2^l)
+        let w = invmod(&((&sig.s << bits as usize) % n), n)?;
+        t_row[i] = rational(&(&sig.r * &w % n));
+        u_row[i] = rational(&((n - hash_message(message, n) * &w % n) % n));
+    }
+    let scale = BigRational::from_integer(BigInt::one() << bits as usize);
+    t_row[size - 2] = scale.recip();
+    u_row[size - 1] = rational(n) / &scale;
+    basis.push(t_row);
+    basis.push(u_row);
+    Some(basis)
+}
+
+pub fn recover_private(params: &Params, public: &Point, signed: &[(Vec<u8>, Signature)], bits: u32) -> Option<BigUint> {
+    let basis = hnp_basis(params, signed, bits)?;
+    let size = basis.len();
+    let cu = basis[size - 1][size - 1].clone();
+    let scale = BigRational::from_integer(BigInt::one() << bits as usize);
+    let n = BigInt::from(params.n.clone());
+    // The short vector could have come out either way up
+    lll(&basis, &ratio(99, 100)).into_iter()
+        .filter(|row| row[size - 1].abs() == cu)
+        .filter_map(|row| {
+            let mut d = (&row[size - 2] * &scale).to_integer();
+            if row[size - 1] == cu {
+                d = -d;
+            }
+            ((d % &n + &n) % &n).to_biguint()
+        })
+        .find(|d| &params.curve.mul(&params.g, d) == public)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecdsa::verify;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn signatures<R: Rng>(params: &Params, d: &BigUint, count: usize, rng: &mut R) -> Vec<(Vec<u8>, Signature)> {
+        (0..count)
+            .map(|i| {
+                let message = format!("Cash rules everything around me {}", i).into_bytes();
+                let sig = sign_biased(params, d, &message, BIASED_BITS, rng);
+                (message, sig)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn biased_signatures_verify() {
+        let mut rng = StdRng::seed_from_u64(62);
+        let params = Params::challenge59();
+        let keys = params.generate_keypair(&mut rng);
+        for (message, sig) in signatures(&params, &keys.private, 4, &mut rng) {
+            assert!(verify(&params, &keys.public, &message, &sig));
+        }
+    }
+
+    // 16 signatures carry the 125 bits of d, but LLL only finds the target if
+    // it's shorter than the lattice's typical shortest vector. That's about
+    // sqrt(dim/2 pi e) det^(1/dim) with dim = k + 2 and det = n^(k+1)/2^2l,
+    // against roughly sqrt(k/3) n/2^l for the target, and for this n and
+    // l = 8 the first one is bigger once k >= 20.
+    #[test]
+    fn challenge62_recover_private() {
+        let mut rng = StdRng::seed_from_u64(62);
+        let params = Params::challenge59();
+        let keys = params.generate_keypair(&mut rng);
+        let signed = signatures(&params, &keys.private, 20, &mut rng);
+        assert_eq!(recover_private(&params, &keys.public, &signed, BIASED_BITS), Some(keys.private));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::One;
+use rand::Rng;
+use set5::numtheory::{crt, invmod, is_probable_prime, primes_below, MR_ROUNDS};
+use set5::rsa::{bytes_to_int, KeyPair as RsaKeyPair, PrivateKey, PublicKey};
+use set6::pkcs1::{encode_signature, HashAlgorithm};
+
+use crate::ec::{KeyPair, Params, Point};
+use crate::ecdsa::{hash_message, Signature};
+
+// Primes below this go into p - 1
+const SMOOTH_BOUND: u32 = 1 << 16;
+
+/*
+ * Duplicate signature key selection
+ *
+ * A signature only says that *some* key signed the message. Given someone
+ * else's (message, signature, public key) we can make up a key pair of our
+ * own that the very same signature verifies under, and claim we signed it.
+ */
+
+/*
+ * ECDSA: verification rebuilds R = u1 G + u2 Q with u1 = H(m)/s and u2 = r/s,
+ * and we get to pick G as well as Q. Pick any d', set t = u1 + u2 d' and
+ *   G' = t^-1 R,  Q' = d' G'
+ * then u1 G' + u2 Q' = (u1 + u2 d') G' = R again.
+ */
+pub fn ecdsa_dsks<R: Rng>(params: &Params, public: &Point, message: &[u8], sig: &Signature, rng: &mut R) -> Option<(Params, KeyPair)> {
+    let n = &params.n;
+    let curve = &params.curve;
+    let w = invmod(&sig.s, n)?;
+    let u1 = hash_message(message, n) * &w % n;
+    let u2 = &sig.r * &w % n;
+    let r = curve.add(&curve.mul(&params.g, &u1), &curve.mul(public, &u2));
+    // Then every t^-1 R is infinity too, and that's no generator
+    if r == Point::Infinity {
+        return None;
+    }
+    std::iter::repeat_with(|| rng.gen_biguint_range(&BigUint::one(), n)).find_map(|private| {
+        let t = invmod(&((&u1 + &u2 * &private) % n), n)?;
+        let forged = Params::new(curve.clone(), curve.mul(&r, &t), n.clone());
+        let keys = forged.keypair_from_private(private);
+        Some((forged, keys))
+    })
+}
+
+/*
+ * RSA: we need s^e' = pad(m) mod N', i.e. e' is the discrete log of pad(m)
+ * base s. That's hard in general, but easy mod a prime p where p - 1 only has
+ * small factors: Pohlig-Hellman solves it in each small subgroup and CRT
+ * glues the answers together. Do that mod p and mod q and glue again.
+ */
+
+// Baby-step giant-step for h = g^x with x below `order`
+pub fn bsgs(g: &BigUint, h: &BigUint, p: &BigUint, order: u32) -> Option<u32> {
+    let m = ((order as f64).sqrt().ceil() as u32).max(1);
+    let mut baby = HashMap::new();
+    let mut gj = BigUint::one();
+    for j in 0..m {
+        baby.entry(gj.clone()).or_insert(j);
+        gj = gj * g % p;
+    }
+    // g^-m
+    let giant = invmod(&gj, p)?;
+    let mut gamma = h.clone();
+    for i in 0..m {
+        if let Some(j) = baby.get(&gamma) {
+            return Some(i * m + j);
+        }
+        gamma = gamma * &giant % p;
+    }
+    None
+}
+
+// x with g^x = h mod p, for p - 1 the product of the (distinct) `factors`
+pub fn pohlig_hellman(g: &BigUint, h: &BigUint, p: &BigUint, factors: &[u32]) -> Option<BigUint> {
+    let order = p - 1u32;
+    let congruences = factors.iter()
+        .map(|&f| {
+            let cofactor = &order / f;
+            let x = bsgs(&g.modpow(&cofactor, p), &h.modpow(&cofactor, p), p, f)?;
+            Some((BigUint::from(x), BigUint::from(f)))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let (x, _) = crt(&congruences)?;
+    Some(x)
+}
+
+// g generates everything mod p when it isn't in any subgroup of prime index
+pub fn is_generator(g: &BigUint, p: &BigUint, factors: &[u32]) -> bool {
+    let order = p - 1u32;
+    factors.iter().all(|&f| !g.modpow(&(&order / f), p).is_one())
+}
+
+// A `bits` bit prime p = 2 f_1 ... f_k + 1 with distinct f_i below the smooth
+// bound and none of them in `avoid`, along with 2, f_1, ..., f_k
+pub fn smooth_prime<R: Rng>(bits: u64, avoid: &[u32], rng: &mut R) -> (BigUint, Vec<u32>) {
+    let primes = primes_below(SMOOTH_BOUND).into_iter()
+        .filter(|f| *f > 2 && !avoid.contains(f))
+        .collect::<Vec<_>>();
+    loop {
+        let mut factors = vec![2];
+        let mut product = BigUint::from(2u32);
+        // Fill up with random primes, then find one that makes the size exact
+        while product.bits() + 16 < bits {
+            let f = primes[rng.gen_range(0, primes.len())];
+            if !factors.contains(&f) {
+                factors.push(f);
+                product *= f;
+            }
+        }
+        let last = primes.iter()
+            .filter(|f| !factors.contains(f))
+            .map(|&f| (f, &product * f + 1u32))
+            .filter(|(_, p)| p.bits() == bits)
+            .find(|(_, p)| is_probable_prime(p, MR_ROUNDS, rng));
+        if let Some((f, p)) = last {
+            factors.push(f);
+            return (p, factors);
+        }
+    }
+}
+
+pub fn rsa_dsks<R: Rng>(public: &PublicKey, hash: HashAlgorithm, message: &[u8], signature: &[u8], rng: &mut R) -> Option<RsaKeyPair> {
+    let bits = public.n.bits();
+    let m = bytes_to_int(&encode_signature(hash, message, public.size()).ok()?);
+    let s = bytes_to_int(signature);
+    loop {
+        // Both primes have to have s as a generator so pad(m) is some power of it
+        let (p, p_factors) = smooth_prime(bits - bits / 2, &[], rng);
+        if !is_generator(&s, &p, &p_factors) {
+            continue;
+        }
+        let (q, q_factors) = smooth_prime(bits / 2, &p_factors, rng);
+        let n = &p * &q;
+        if n.bits() != bits || s >= n || !is_generator(&s, &q, &q_factors) {
+            continue;
+        }
+
+        // p - 1 and q - 1 only share the 2, so the exponents have to agree mod 2
+        let ep = pohlig_hellman(&s, &m, &p, &p_factors)?;
+        let eq = pohlig_hellman(&s, &m, &q, &q_factors)?;
+        if ep.is_odd() != eq.is_odd() {
+            continue;
+        }
+        let half_q = (&q - 1u32) >> 1;
+        let (e, lambda) = crt(&[(ep, &p - 1u32), (&eq % &half_q, half_q)])?;
+        let d = match invmod(&e, &lambda) {
+            Some(d) => d,
+            None => continue,
+        };
+        return Some(RsaKeyPair {
+            public: PublicKey { e, n: n.clone() },
+            private: PrivateKey { d, n },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecdsa::{sign, verify};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::rsa::generate;
+    use set6::pkcs1;
+
+    const MESSAGE: &[u8] = b"Protect ya neck";
+
+    #[test]
+    fn challenge61_ecdsa() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let params = Params::challenge59();
+        let alice = params.generate_keypair(&mut rng);
+        let sig = sign(&params, &alice.private, MESSAGE, &mut rng);
+        assert!(verify(&params, &alice.public, MESSAGE, &sig));
+
+        let (forged, eve) = ecdsa_dsks(&params, &alice.public, MESSAGE, &sig, &mut rng).unwrap();
+        assert_ne!(forged.g, params.g);
+        assert_ne!(eve.public, alice.public);
+        assert!(verify(&forged, &eve.public, MESSAGE, &sig));
+        // Eve's key is real, she can sign with it
+        let own = sign(&forged, &eve.private, b"Shame on a", &mut rng);
+        assert!(verify(&forged, &eve.public, b"Shame on a", &own));
+    }
+
+    // With d = -H(m)/r, u1 G + u2 Q = (H(m) - H(m))/s G is infinity
+    #[test]
+    fn ecdsa_dsks_rejects_infinity() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let params = Params::challenge59();
+        let n = &params.n;
+        let sig = Signature { r: BigUint::from(5u32), s: BigUint::from(7u32) };
+        let d = (n - hash_message(MESSAGE, n) * invmod(&sig.r, n).unwrap() % n) % n;
+        let public = params.curve.mul(&params.g, &d);
+        assert_eq!(ecdsa_dsks(&params, &public, MESSAGE, &sig, &mut rng), None);
+    }
+
+    #[test]
+    fn pohlig_hellman_smooth_prime() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let (p, factors) = smooth_prime(256, &[], &mut rng);
+        assert_eq!(p.bits(), 256);
+        assert_eq!(factors.iter().fold(BigUint::one(), |acc, &f| acc * f) + 1u32, p);
+        let g = (2u32..).map(BigUint::from).find(|g| is_generator(g, &p, &factors)).unwrap();
+        let x = rng.gen_biguint_below(&(&p - 1u32));
+        assert_eq!(pohlig_hellman(&g, &g.modpow(&x, &p), &p, &factors), Some(x));
+    }
+
+    #[test]
+    fn challenge61_rsa() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let alice = generate(1024, 65537, &mut rng);
+        let signature = pkcs1::sign(&alice.private, HashAlgorithm::Sha256, MESSAGE).unwrap();
+        assert!(pkcs1::verify(&alice.public, HashAlgorithm::Sha256, MESSAGE, &signature));
+
+        let eve = rsa_dsks(&alice.public, HashAlgorithm::Sha256, MESSAGE, &signature, &mut rng).unwrap();
+        assert_ne!(eve.public.n, alice.public.n);
+        assert!(pkcs1::verify(&eve.public, HashAlgorithm::Sha256, MESSAGE, &signature));
+        assert_eq!(pkcs1::sign(&eve.private, HashAlgorithm::Sha256, MESSAGE).unwrap(), signature);
+    }
+}
@@ -0,0 +1,341 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+
+/*
+ * Short Weierstrass curves
+ *
+ * y^2 = x^3 + ax + b over GF(p). Two points add by drawing the line through
+ * them, finding where it meets the curve again and flipping that over the x
+ * axis, with the point at infinity as the identity.
+ *
+ * Affine additions each need an inversion, which is most of their cost.
+ * Jacobian coordinates (X, Y, Z) stand for (X/Z^2, Y/Z^3), so a whole scalar
+ * multiplication only inverts once at the end.
+ *
+ * None of the formulas use b. Hand a point from some other curve with the
+ * same a to code that doesn't check, and it does arithmetic on that curve.
+ */
+
+const CHALLENGE59_P: &str = "233970423115425145524320034830162017933";
+const CHALLENGE59_GY: &str = "85518893674295321206118380980485522083";
+const CHALLENGE59_N: &str = "29246302889428143187362802287225875743";
+
+fn dec(s: &str) -> BigUint {
+    BigUint::parse_bytes(s.as_bytes(), 10).unwrap()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Point {
+    Infinity,
+    Affine(BigUint, BigUint),
+}
+
+// Z = 0 is the point at infinity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jacobian {
+    pub x: BigUint,
+    pub y: BigUint,
+    pub z: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Curve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+}
+
+impl Curve {
+    pub fn new(p: BigUint, a: BigUint, b: BigUint) -> Self {
+        Curve { p, a, b }
+    }
+
+    // y^2 = x^3 - 95051x + 11279326
+    pub fn challenge59() -> Self {
+        let p = dec(CHALLENGE59_P);
+        let a = &p - 95051u32;
+        Curve::new(p, a, BigUint::from(11279326u32))
+    }
+
+    // Same p and a, different b: points on it go through the same formulas
+    pub fn with_b(&self, b: BigUint) -> Self {
+        Curve { b, ..self.clone() }
+    }
+
+    // Fermat: p is prime, so a^(p-2) is 1/a. Unlike invmod it doesn't notice
+    // a = 0 and just hands back 0, so callers have to deal with infinity first.
+    pub fn inv(&self, a: &BigUint) -> BigUint {
+        debug_assert!(!(a % &self.p).is_zero(), "0 has no inverse");
+        a.modpow(&(&self.p - 2u32), &self.p)
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.p - b) % &self.p
+    }
+
+    // x^3 + ax + b
+    pub fn rhs(&self, x: &BigUint) -> BigUint {
+        (x * x % &self.p * x + &self.a * x + &self.b) % &self.p
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match point {
+            Point::Infinity => true,
+            Point::Affine(x, y) => y * y % &self.p == self.rhs(x),
+        }
+    }
+
+    pub fn negate(&self, point: &Point) -> Point {
+        match point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine(x, y) => Point::Affine(x.clone(), (&self.p - y) % &self.p),
+        }
+    }
+
+    pub fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let (x1, y1, x2, y2) = match (p1, p2) {
+            (Point::Infinity, _) => return p2.clone(),
+            (_, Point::Infinity) => return p1.clone(),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => (x1, y1, x2, y2),
+        };
+        if p2 == &self.negate(p1) {
+            return Point::Infinity;
+        }
+        let slope = if p1 == p2 {
+            (x1 * x1 * 3u32 + &self.a) * self.inv(&(y1 * 2u32 % &self.p))
+        } else {
+            self.sub(y2, y1) * self.inv(&self.sub(x2, x1))
+        } % &self.p;
+        let x3 = self.sub(&(&slope * &slope), &(x1 + x2));
+        let y3 = self.sub(&(slope * self.sub(x1, &x3)), y1);
+        Point::Affine(x3, y3)
+    }
+
+    pub fn to_jacobian(&self, point: &Point) -> Jacobian {
+        match point {
+            Point::Infinity => Jacobian { x: BigUint::one(), y: BigUint::one(), z: BigUint::zero() },
+            Point::Affine(x, y) => Jacobian { x: x.clone(), y: y.clone(), z: BigUint::one() },
+        }
+    }
+
+    pub fn to_affine(&self, point: &Jacobian) -> Point {
+        if point.z.is_zero() {
+            return Point::Infinity;
+        }
+        let p = &self.p;
+        let z_inv = self.inv(&point.z);
+        let z_inv2 = &z_inv * &z_inv % p;
+        Point::Affine(&point.x * &z_inv2 % p, &point.y * z_inv2 * z_inv % p)
+    }
+
+    pub fn double_jacobian(&self, point: &Jacobian) -> Jacobian {
+        let p = &self.p;
+        let Jacobian { x, y, z } = point;
+        if z.is_zero() || y.is_zero() {
+            return self.to_jacobian(&Point::Infinity);
+        }
+        let y2 = y * y % p;
+        let s = x * &y2 * 4u32 % p;
+        let z2 = z * z % p;
+        let m = (x * x * 3u32 + &self.a * &z2 % p * &z2) % p;
+        let x3 = self.sub(&(&m * &m % p), &(&s * 2u32 % p));
+        let y3 = self.sub(&(m * self.sub(&s, &x3) % p), &(&y2 * &y2 * 8u32 % p));
+        let z3 = y * z * 2u32 % p;
+        Jacobian { x: x3, y: y3, z: z3 }
+    }
+
+    pub fn add_jacobian(&self, p1: &Jacobian, p2: &Jacobian) -> Jacobian {
+        if p1.z.is_zero() {
+            return p2.clone();
+        }
+        if p2.z.is_zero() {
+            return p1.clone();
+        }
+        let p = &self.p;
+        let (z1z1, z2z2) = (&p1.z * &p1.z % p, &p2.z * &p2.z % p);
+        let u1 = &p1.x * &z2z2 % p;
+        let u2 = &p2.x * &z1z1 % p;
+        let s1 = &p1.y * &p2.z % p * z2z2 % p;
+        let s2 = &p2.y * &p1.z % p * z1z1 % p;
+        if u1 == u2 {
+            return if s1 == s2 { self.double_jacobian(p1) } else { self.to_jacobian(&Point::Infinity) };
+        }
+        let h = self.sub(&u2, &u1);
+        let r = self.sub(&s2, &s1);
+        let h2 = &h * &h % p;
+        let h3 = &h2 * &h % p;
+        let u1h2 = u1 * h2 % p;
+        let x3 = self.sub(&self.sub(&(&r * &r % p), &h3), &(&u1h2 * 2u32 % p));
+        let y3 = self.sub(&(r * self.sub(&u1h2, &x3) % p), &(s1 * h3 % p));
+        let z3 = h * &p1.z % p * &p2.z % p;
+        Jacobian { x: x3, y: y3, z: z3 }
+    }
+
+    // Double and add, left to right, in Jacobian coordinates
+    pub fn mul(&self, point: &Point, k: &BigUint) -> Point {
+        let base = self.to_jacobian(point);
+        let mut result = self.to_jacobian(&Point::Infinity);
+        for i in (0..k.bits()).rev() {
+            result = self.double_jacobian(&result);
+            if k.bit(i) {
+                result = self.add_jacobian(&result, &base);
+            }
+        }
+        self.to_affine(&result)
+    }
+
+    // Random x until x^3 + ax + b has a square root
+    pub fn random_point<R: Rng>(&self, rng: &mut R) -> Point {
+        loop {
+            let x = rng.gen_biguint_below(&self.p);
+            if let Some(y) = sqrt_mod(&self.rhs(&x), &self.p) {
+                return Point::Affine(x, y);
+            }
+        }
+    }
+}
+
+// Tonelli-Shanks, for odd prime p
+pub fn sqrt_mod(n: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let n = n % p;
+    if n.is_zero() {
+        return Some(n);
+    }
+    let p_minus_one = p - 1u32;
+    let half = &p_minus_one >> 1;
+    if !n.modpow(&half, p).is_one() {
+        return None;
+    }
+    // p - 1 = q 2^s with q odd, and z any non-residue
+    let s = p_minus_one.trailing_zeros().unwrap();
+    let q = &p_minus_one >> s;
+    let z = (2u32..).map(BigUint::from).find(|z| z.modpow(&half, p) == p_minus_one).unwrap();
+
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = n.modpow(&q, p);
+    let mut r = n.modpow(&((&q + 1u32) >> 1), p);
+    while !t.is_one() {
+        let mut i = 0;
+        let mut t2i = t.clone();
+        while !t2i.is_one() {
+            t2i = &t2i * &t2i % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * b % p;
+    }
+    Some(r)
+}
+
+/*
+ * ECDH
+ *
+ * Same as finite-field DH with scalar multiplication in place of
+ * exponentiation: publish xG, and both sides end up at xyG.
+ */
+
+// Curve, base point and the order of the base point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    pub curve: Curve,
+    pub g: Point,
+    pub n: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    pub private: BigUint,
+    pub public: Point,
+}
+
+impl Params {
+    pub fn new(curve: Curve, g: Point, n: BigUint) -> Self {
+        Params { curve, g, n }
+    }
+
+    pub fn challenge59() -> Self {
+        let g = Point::Affine(BigUint::from(182u32), dec(CHALLENGE59_GY));
+        Params::new(Curve::challenge59(), g, dec(CHALLENGE59_N))
+    }
+
+    pub fn generate_keypair<R: Rng>(&self, rng: &mut R) -> KeyPair {
+        let private = rng.gen_biguint_range(&BigUint::one(), &self.n);
+        self.keypair_from_private(private)
+    }
+
+    pub fn keypair_from_private(&self, private: BigUint) -> KeyPair {
+        let public = self.curve.mul(&self.g, &private);
+        KeyPair { private, public }
+    }
+
+    pub fn shared_secret(&self, private: &BigUint, public: &Point) -> Point {
+        self.curve.mul(public, private)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn sqrt_mod_small_primes() {
+        for &p in &[7u32, 13, 17, 41, 97] {
+            for n in 0..p {
+                let root = sqrt_mod(&BigUint::from(n), &BigUint::from(p));
+                assert_eq!(root.is_some(), (0..p).any(|r| r * r % p == n));
+                if let Some(root) = root {
+                    assert_eq!(&root * &root % p, BigUint::from(n));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn challenge59_base_point() {
+        let params = Params::challenge59();
+        assert!(params.curve.contains(&params.g));
+        assert_eq!(params.curve.mul(&params.g, &params.n), Point::Infinity);
+        assert_ne!(params.curve.mul(&params.g, &(&params.n - 1u32)), Point::Infinity);
+    }
+
+    #[test]
+    fn mul_matches_repeated_addition() {
+        let params = Params::challenge59();
+        let curve = &params.curve;
+        let mut sum = Point::Infinity;
+        for k in 0..40u32 {
+            assert_eq!(curve.mul(&params.g, &BigUint::from(k)), sum);
+            assert!(curve.contains(&sum));
+            sum = curve.add(&sum, &params.g);
+        }
+    }
+
+    #[test]
+    fn jacobian_round_trip() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let curve = Curve::challenge59();
+        let (p1, p2) = (curve.random_point(&mut rng), curve.random_point(&mut rng));
+        let (j1, j2) = (curve.to_jacobian(&p1), curve.to_jacobian(&p2));
+        assert_eq!(curve.to_affine(&curve.add_jacobian(&j1, &j2)), curve.add(&p1, &p2));
+        assert_eq!(curve.to_affine(&curve.double_jacobian(&j1)), curve.add(&p1, &p1));
+        assert_eq!(curve.add(&p1, &curve.negate(&p1)), Point::Infinity);
+    }
+
+    #[test]
+    fn challenge59_ecdh() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let params = Params::challenge59();
+        let alice = params.generate_keypair(&mut rng);
+        let bob = params.generate_keypair(&mut rng);
+        assert!(params.curve.contains(&alice.public));
+        assert_eq!(params.shared_secret(&alice.private, &bob.public),
+                   params.shared_secret(&bob.private, &alice.public));
+    }
+}
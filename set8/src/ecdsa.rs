@@ -0,0 +1,101 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::numtheory::invmod;
+use sha2::{Digest, Sha256};
+
+use crate::ec::{Params, Point};
+
+/*
+ * ECDSA
+ *
+ * DSA with scalar multiplication in place of exponentiation. Signing picks a
+ * nonce k and computes
+ *   r = x(kG) mod n
+ *   s = k^-1 (H(m) + dr) mod n
+ * and verification checks that x(H(m)/s G + r/s Q) mod n comes back to r.
+ */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+// SHA-256, cut down to the leftmost bits of n like the standard says
+pub fn hash_message(message: &[u8], n: &BigUint) -> BigUint {
+    let h = BigUint::from_bytes_be(&Sha256::digest(message));
+    let bits = n.bits();
+    if bits < 256 {
+        h >> (256 - bits)
+    } else {
+        h
+    }
+}
+
+pub fn sign_with_nonce(params: &Params, d: &BigUint, h: &BigUint, k: &BigUint) -> Option<Signature> {
+    let n = &params.n;
+    let r = match params.curve.mul(&params.g, k) {
+        Point::Infinity => return None,
+        Point::Affine(x, _) => x % n,
+    };
+    let s = invmod(k, n)? * (h + d * &r) % n;
+    if r.is_zero() || s.is_zero() {
+        return None;
+    }
+    Some(Signature { r, s })
+}
+
+pub fn sign<R: Rng>(params: &Params, d: &BigUint, message: &[u8], rng: &mut R) -> Signature {
+    let h = hash_message(message, &params.n);
+    loop {
+        let k = rng.gen_biguint_range(&BigUint::one(), &params.n);
+        if let Some(sig) = sign_with_nonce(params, d, &h, &k) {
+            return sig;
+        }
+    }
+}
+
+pub fn verify(params: &Params, public: &Point, message: &[u8], sig: &Signature) -> bool {
+    let n = &params.n;
+    let in_range = |v: &BigUint| !v.is_zero() && v < n;
+    if !in_range(&sig.r) || !in_range(&sig.s) {
+        return false;
+    }
+    let w = match invmod(&sig.s, n) {
+        Some(w) => w,
+        None => return false,
+    };
+    let u1 = hash_message(message, n) * &w % n;
+    let u2 = &sig.r * &w % n;
+    let curve = &params.curve;
+    match curve.add(&curve.mul(&params.g, &u1), &curve.mul(public, &u2)) {
+        Point::Infinity => false,
+        Point::Affine(x, _) => x % n == sig.r,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn sign_and_verify() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let params = Params::challenge59();
+        let keys = params.generate_keypair(&mut rng);
+        let sig = sign(&params, &keys.private, b"Protect ya neck", &mut rng);
+        assert!(verify(&params, &keys.public, b"Protect ya neck", &sig));
+        assert!(!verify(&params, &keys.public, b"Protect ya face", &sig));
+        let other = params.generate_keypair(&mut rng);
+        assert!(!verify(&params, &other.public, b"Protect ya neck", &sig));
+    }
+
+    #[test]
+    fn hash_fits_in_n() {
+        let params = Params::challenge59();
+        assert!(hash_message(b"Protect ya neck", &params.n).bits() <= params.n.bits());
+    }
+}
@@ -0,0 +1,177 @@
+use std::cell::Cell;
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::numtheory::crt;
+use set5::rsa::int_to_bytes;
+
+use crate::ec::{Curve, KeyPair, Params, Point};
+use crate::subgroup::{mac, small_factors, MESSAGE, SMALL_FACTOR_LIMIT};
+
+/*
+ * Invalid curve attack
+ *
+ * The challenge 59 curve has a prime order subgroup and a cofactor of 8, so
+ * there's nothing to confine Bob to. But his scalar multiplication never
+ * looks at b, and he never checks the point we send him is on his curve.
+ * Curves with a different b have different orders, and plenty of them have
+ * small factors: send Bob a point of small order r from one of those, and his
+ * MAC gives away x mod r just like in challenge 57.
+ */
+
+// b and the number of points on y^2 = x^3 - 95051x + b
+pub const CHALLENGE59_INVALID_CURVES: [(u32, &str); 3] = [
+    (210, "233970423115425145550826547352470124412"),
+    (504, "233970423115425145544350131142039591210"),
+    (727, "233970423115425145545378039958152057148"),
+];
+
+pub fn invalid_curves(params: &Params) -> Vec<(Curve, BigUint)> {
+    CHALLENGE59_INVALID_CURVES.iter()
+        .map(|&(b, order)| (params.curve.with_b(BigUint::from(b)), BigUint::parse_bytes(order.as_bytes(), 10).unwrap()))
+        .collect()
+}
+
+// Both coordinates, so a point and its negation key different MACs.
+// Infinity gets the empty key.
+pub fn shared_key(curve: &Curve, point: &Point) -> Vec<u8> {
+    let len = (curve.p.bits() as usize).div_ceil(8);
+    match point {
+        Point::Infinity => vec![],
+        Point::Affine(x, y) => [int_to_bytes(x, len), int_to_bytes(y, len)].concat(),
+    }
+}
+
+pub struct Bob {
+    params: Params,
+    keys: KeyPair,
+    queries: Cell<usize>,
+}
+
+impl Bob {
+    pub fn new<R: Rng>(params: Params, rng: &mut R) -> Self {
+        let keys = params.generate_keypair(rng);
+        Bob { params, keys, queries: Cell::new(0) }
+    }
+
+    pub fn public(&self) -> &Point {
+        &self.keys.public
+    }
+
+    // (message, MAC(K, message)) with K = xh, whatever curve h is on
+    pub fn respond(&self, h: &Point) -> (Vec<u8>, Vec<u8>) {
+        self.queries.set(self.queries.get() + 1);
+        let key = self.params.shared_secret(&self.keys.private, h);
+        (MESSAGE.to_vec(), mac(&shared_key(&self.params.curve, &key), MESSAGE))
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+// A point of order r on a curve with `order` points, for r a prime dividing
+// it. Multiplying by order / r isn't enough when r divides the order more than
+// once: with two points of order 2, half the order already kills everything.
+// Strip every factor of r instead, then multiply by r until one more would hit
+// infinity.
+pub fn point_of_order<R: Rng>(curve: &Curve, order: &BigUint, r: u32, rng: &mut R) -> Point {
+    let mut cofactor = order.clone();
+    while (&cofactor % r).is_zero() {
+        cofactor /= r;
+    }
+    loop {
+        let mut h = curve.mul(&curve.random_point(rng), &cofactor);
+        if h == Point::Infinity {
+            continue;
+        }
+        loop {
+            let next = curve.mul(&h, &BigUint::from(r));
+            if next == Point::Infinity {
+                return h;
+            }
+            h = next;
+        }
+    }
+}
+
+// The k in [0, r) with MAC(kh, message) = tag
+fn brute_force(curve: &Curve, h: &Point, r: u32, message: &[u8], tag: &[u8]) -> Option<u32> {
+    let mut kh = Point::Infinity;
+    for k in 0..r {
+        if mac(&shared_key(curve, &kh), message) == tag {
+            return Some(k);
+        }
+        kh = curve.add(&kh, h);
+    }
+    None
+}
+
+// Every small factor of every invalid curve's order we haven't already got,
+// until the product passes the order of the base point
+pub fn recover_private<R: Rng>(bob: &Bob, params: &Params, curves: &[(Curve, BigUint)], rng: &mut R) -> Option<BigUint> {
+    let mut congruences = vec![];
+    let mut modulus = BigUint::one();
+    for (curve, order) in curves {
+        for r in small_factors(order, SMALL_FACTOR_LIMIT) {
+            if modulus > params.n {
+                break;
+            }
+            if (&modulus % r).is_zero() {
+                continue;
+            }
+            let h = point_of_order(curve, order, r, rng);
+            let (message, tag) = bob.respond(&h);
+            let residue = brute_force(curve, &h, r, &message, &tag)?;
+            congruences.push((BigUint::from(residue), BigUint::from(r)));
+            modulus *= r;
+        }
+    }
+    let (x, modulus) = crt(&congruences)?;
+    if modulus > params.n {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn challenge59_invalid_curve_orders() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let params = Params::challenge59();
+        for (curve, order) in invalid_curves(&params) {
+            let point = curve.random_point(&mut rng);
+            assert!(curve.contains(&point));
+            assert!(!params.curve.contains(&point));
+            assert_eq!(curve.mul(&point, &order), Point::Infinity);
+        }
+    }
+
+    #[test]
+    fn challenge59_point_of_order() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let params = Params::challenge59();
+        let (curve, order) = &invalid_curves(&params)[0];
+        for &r in &[2, 3, 11, 89] {
+            let h = point_of_order(curve, order, r, &mut rng);
+            assert_eq!(curve.mul(&h, &BigUint::from(r)), Point::Infinity);
+        }
+    }
+
+    #[test]
+    fn challenge59_recover_private() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let params = Params::challenge59();
+        let bob = Bob::new(params.clone(), &mut rng);
+        let x = recover_private(&bob, &params, &invalid_curves(&params), &mut rng).unwrap();
+        assert_eq!(x, bob.keys.private);
+        assert_eq!(&params.curve.mul(&params.g, &x), bob.public());
+    }
+}
@@ -0,0 +1,213 @@
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use rand::Rng;
+
+use crate::ec::{Curve, Point};
+use crate::subgroup::{leak_residues, Bob, Params, SMALL_FACTOR_LIMIT};
+
+/*
+ * Pollard's kangaroo
+ *
+ * Finds x in [a, b] with y = g^x in about sqrt(b - a) steps. Both kangaroos
+ * hop from element to element, each hop decided by the element they're on,
+ * and keep track of how far they've gone. The tame one starts at g^b and sets
+ * a trap where it stops. The wild one starts at y: once it lands anywhere the
+ * tame one did it follows the same path into the trap, and the difference in
+ * distance travelled gives x. If it hops past the end of the interval without
+ * getting caught, x wasn't there (or we were unlucky).
+ */
+
+// Just enough of a cyclic group to hop around in. `index` maps an element to a
+// number that picks its jump, it only needs to be deterministic and spread out.
+pub trait Group {
+    type Element: Clone + PartialEq;
+
+    fn combine(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    fn exp(&self, a: &Self::Element, k: &BigUint) -> Self::Element;
+    fn index(&self, a: &Self::Element) -> u64;
+}
+
+impl Group for Params {
+    type Element = BigUint;
+
+    fn combine(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % &self.p
+    }
+
+    fn exp(&self, a: &BigUint, k: &BigUint) -> BigUint {
+        a.modpow(k, &self.p)
+    }
+
+    fn index(&self, a: &BigUint) -> u64 {
+        a.iter_u64_digits().next().unwrap_or(0)
+    }
+}
+
+impl Group for Curve {
+    type Element = Point;
+
+    fn combine(&self, a: &Point, b: &Point) -> Point {
+        self.add(a, b)
+    }
+
+    fn exp(&self, a: &Point, k: &BigUint) -> Point {
+        self.mul(a, k)
+    }
+
+    fn index(&self, a: &Point) -> u64 {
+        match a {
+            Point::Infinity => 0,
+            Point::Affine(x, _) => x.iter_u64_digits().next().unwrap_or(0),
+        }
+    }
+}
+
+// The jump from y is sizes[index(y) mod k]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jumps {
+    sizes: Vec<u64>,
+}
+
+impl Jumps {
+    pub fn new(sizes: Vec<u64>) -> Self {
+        assert!(!sizes.is_empty(), "no jumps");
+        Jumps { sizes }
+    }
+
+    // 1, 2, 4, ..., 2^(k-1)
+    pub fn powers_of_two(k: u32) -> Self {
+        Jumps::new((0..k).map(|i| 1 << i).collect())
+    }
+
+    // Powers of two with a mean jump of about sqrt(b - a) / 2
+    pub fn for_width(width: &BigUint) -> Self {
+        let target = width.sqrt() / 2u32;
+        let k = (1..63).find(|&k| BigUint::from(Jumps::powers_of_two(k).mean()) >= target).unwrap_or(63);
+        Jumps::powers_of_two(k)
+    }
+
+    pub fn mean(&self) -> u64 {
+        self.sizes.iter().sum::<u64>() / self.sizes.len() as u64
+    }
+
+    // How long the tame kangaroo runs for
+    pub fn tame_steps(&self) -> u64 {
+        4 * self.mean()
+    }
+
+    fn pick<G: Group>(&self, group: &G, y: &G::Element) -> usize {
+        (group.index(y) % self.sizes.len() as u64) as usize
+    }
+}
+
+pub fn kangaroo<G: Group>(group: &G, g: &G::Element, y: &G::Element, a: &BigUint, b: &BigUint, jumps: &Jumps) -> Option<BigUint> {
+    let hops = jumps.sizes.iter().map(|&size| group.exp(g, &BigUint::from(size))).collect::<Vec<_>>();
+
+    let mut tame = group.exp(g, b);
+    let mut tame_distance = BigUint::zero();
+    for _ in 0..jumps.tame_steps() {
+        let i = jumps.pick(group, &tame);
+        tame_distance += jumps.sizes[i];
+        tame = group.combine(&tame, &hops[i]);
+    }
+
+    let mut wild = y.clone();
+    let mut wild_distance = BigUint::zero();
+    let limit = b - a + &tame_distance;
+    while wild_distance <= limit {
+        if wild == tame {
+            return Some(b + tame_distance - wild_distance);
+        }
+        let i = jumps.pick(group, &wild);
+        wild_distance += jumps.sizes[i];
+        wild = group.combine(&wild, &hops[i]);
+    }
+    None
+}
+
+/*
+ * Challenge 58: the small factors of j only get us x mod r for some r well
+ * short of q. Writing x = n + mr,
+ *   y' = y g^-n = (g^r)^m
+ * with m somewhere in [0, (q - 1) / r], which is small enough to catch.
+ */
+pub fn recover_private<R: Rng>(bob: &Bob, params: &Params, rng: &mut R) -> Option<BigUint> {
+    let (n, r) = leak_residues(bob, params, SMALL_FACTOR_LIMIT, rng)?;
+    if r > params.q {
+        return Some(n);
+    }
+    let g = params.g.modpow(&r, &params.p);
+    let y = bob.public() * params.g.modpow(&(&params.q - &n % &params.q), &params.p) % &params.p;
+    let width = (&params.q - BigUint::one()) / &r;
+    let m = kangaroo(params, &g, &y, &BigUint::zero(), &width, &Jumps::for_width(&width))?;
+    Some(n + m * r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn dec(s: &str) -> BigUint {
+        BigUint::parse_bytes(s.as_bytes(), 10).unwrap()
+    }
+
+    #[test]
+    fn jumps_for_width() {
+        let jumps = Jumps::for_width(&(BigUint::one() << 20));
+        assert_eq!(jumps, Jumps::powers_of_two(13));
+        assert!(jumps.mean() >= 1 << 9);
+    }
+
+    #[test]
+    fn small_interval() {
+        let params = Params::challenge58();
+        let x = BigUint::from(1234567u32);
+        let y = params.g.modpow(&x, &params.p);
+        let (a, b) = (BigUint::from(1000000u32), BigUint::from(2000000u32));
+        let jumps = Jumps::for_width(&(&b - &a));
+        assert_eq!(kangaroo(&params, &params.g, &y, &a, &b, &jumps), Some(x));
+    }
+
+    #[test]
+    fn elliptic_curve_interval() {
+        let params = crate::ec::Params::challenge59();
+        let x = BigUint::from(987654u32);
+        let y = params.curve.mul(&params.g, &x);
+        let b = BigUint::one() << 20;
+        let jumps = Jumps::for_width(&b);
+        assert_eq!(kangaroo(&params.curve, &params.g, &y, &BigUint::zero(), &b, &jumps), Some(x));
+    }
+
+    #[test]
+    fn challenge58_y_in_20_bits() {
+        let params = Params::challenge58();
+        let y = dec("\
+            7760073848032689505395005705677365876654629189298052775754597607446617558600394076764814236\
+            081991643094239886772481052254010323780165093955236429914607119");
+        let b = BigUint::one() << 20;
+        let x = kangaroo(&params, &params.g, &y, &BigUint::zero(), &b, &Jumps::for_width(&b)).unwrap();
+        assert_eq!(params.g.modpow(&x, &params.p), y);
+    }
+
+    #[test]
+    fn challenge58_y_in_40_bits() {
+        let params = Params::challenge58();
+        let y = dec("\
+            9388897478013399550694114614498790691034187453089355259602614074132918843899833277397448144\
+            245883225611726912025846772975325932794909655215329941809013733");
+        let b = BigUint::one() << 40;
+        let x = kangaroo(&params, &params.g, &y, &BigUint::zero(), &b, &Jumps::for_width(&b)).unwrap();
+        assert_eq!(params.g.modpow(&x, &params.p), y);
+    }
+
+    #[test]
+    fn challenge58_recover_private() {
+        let mut rng = StdRng::seed_from_u64(58);
+        let params = Params::challenge58();
+        let bob = Bob::new(params.clone(), &mut rng);
+        let x = recover_private(&bob, &params, &mut rng).unwrap();
+        assert_eq!(&params.g.modpow(&x, &params.p), bob.public());
+    }
+}
@@ -0,0 +1,10 @@
+pub mod biased_nonce;
+pub mod dsks;
+pub mod ec;
+pub mod ecdsa;
+pub mod invalid_curve;
+pub mod kangaroo;
+pub mod lll;
+pub mod montgomery;
+pub mod subgroup;
+pub mod twist;
@@ -0,0 +1,252 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, Zero};
+
+/*
+ * LLL lattice reduction
+ *
+ * Turns a basis of a lattice into one whose vectors are short and close to
+ * orthogonal. Gram-Schmidt gives the orthogonal b*_i and the coefficients
+ *   mu_ij = <b_i, b*_j> / <b*_j, b*_j>
+ * and LLL keeps two things true as it walks k up the basis:
+ *   size reduction: |mu_kj| <= 1/2, by subtracting round(mu_kj) b_j from b_k
+ *   Lovasz: |b*_k|^2 >= (delta - mu_k,k-1^2) |b*_k-1|^2, or b_k and b_k-1 swap
+ * Recomputing Gram-Schmidt after every change is far too slow, so this
+ * follows Cohen (A Course in Computational Algebraic Number Theory, 2.6.3) and
+ * patches up mu and the |b*_i|^2 in place instead. The b*_i themselves never
+ * get built: their entries have enormous denominators, and inner products of
+ * the b_i are all mu needs. Everything is exact rationals: floating point
+ * loses too much with numbers the size of a curve order.
+ */
+
+pub type Vector = Vec<BigRational>;
+
+pub fn ratio(numer: i64, denom: i64) -> BigRational {
+    BigRational::new(BigInt::from(numer), BigInt::from(denom))
+}
+
+pub fn dot(a: &[BigRational], b: &[BigRational]) -> BigRational {
+    a.iter().zip(b).fold(BigRational::zero(), |acc, (x, y)| acc + x * y)
+}
+
+// a -= c b
+fn sub_multiple(a: &mut Vector, b: &[BigRational], c: &BigRational) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x -= c * y;
+    }
+}
+
+// The orthogonal b*_i and the mu_ij (for j < i) of a basis
+pub fn gram_schmidt(basis: &[Vector]) -> (Vec<Vector>, Vec<Vec<BigRational>>) {
+    let n = basis.len();
+    let mut orthogonal: Vec<Vector> = Vec::with_capacity(n);
+    let mut norms: Vec<BigRational> = Vec::with_capacity(n);
+    let mut mu = vec![vec![BigRational::zero(); n]; n];
+    for (i, b) in basis.iter().enumerate() {
+        let mut v = b.clone();
+        for j in 0..i {
+            mu[i][j] = dot(b, &orthogonal[j]) / &norms[j];
+            sub_multiple(&mut v, &orthogonal[j], &mu[i][j]);
+        }
+        norms.push(dot(&v, &v));
+        orthogonal.push(v);
+    }
+    (orthogonal, mu)
+}
+
+pub fn is_reduced(basis: &[Vector], delta: &BigRational) -> bool {
+    let (orthogonal, mu) = gram_schmidt(basis);
+    let half = ratio(1, 2);
+    let size_reduced = (1..basis.len()).all(|i| (0..i).all(|j| mu[i][j].abs() <= half));
+    let lovasz = (1..basis.len()).all(|k| {
+        dot(&orthogonal[k], &orthogonal[k]) >= (delta - &mu[k][k - 1] * &mu[k][k - 1]) * dot(&orthogonal[k - 1], &orthogonal[k - 1])
+    });
+    size_reduced && lovasz
+}
+
+struct Reduction {
+    basis: Vec<Vector>,
+    mu: Vec<Vec<BigRational>>,
+    // |b*_i|^2
+    norms: Vec<BigRational>,
+}
+
+impl Reduction {
+    // Gram-Schmidt for b_k, given it's already done for everything before,
+    // from inner products of the b_i alone:
+    //   mu_kj = (<b_k, b_j> - sum_i<j mu_ji mu_ki |b*_i|^2) / |b*_j|^2
+    fn orthogonalise(&mut self, k: usize) {
+        for j in 0..k {
+            let mut inner = dot(&self.basis[k], &self.basis[j]);
+            for i in 0..j {
+                inner -= &self.mu[j][i] * &self.mu[k][i] * &self.norms[i];
+            }
+            self.mu[k][j] = inner / &self.norms[j];
+        }
+        let mut norm = dot(&self.basis[k], &self.basis[k]);
+        for j in 0..k {
+            norm -= &self.mu[k][j] * &self.mu[k][j] * &self.norms[j];
+        }
+        self.norms[k] = norm;
+    }
+
+    // Size reduce b_k against b_l. b*_k doesn't move, only the mu do.
+    fn reduce(&mut self, k: usize, l: usize) {
+        if self.mu[k][l].abs() <= ratio(1, 2) {
+            return;
+        }
+        let q = self.mu[k][l].round();
+        let b = self.basis[l].clone();
+        sub_multiple(&mut self.basis[k], &b, &q);
+        self.mu[k][l] -= &q;
+        for i in 0..l {
+            let m = &q * &self.mu[l][i];
+            self.mu[k][i] -= m;
+        }
+    }
+
+    // Swap b_k and b_k-1 and fix up everything that depends on their order,
+    // which is only rows and columns k - 1 and k
+    fn swap(&mut self, k: usize, kmax: usize) {
+        self.basis.swap(k, k - 1);
+        for j in 0..k - 1 {
+            let m = std::mem::replace(&mut self.mu[k][j], BigRational::zero());
+            self.mu[k][j] = std::mem::replace(&mut self.mu[k - 1][j], m);
+        }
+        let m = self.mu[k][k - 1].clone();
+        let norm = &self.norms[k] + &m * &m * &self.norms[k - 1];
+        self.mu[k][k - 1] = &m * &self.norms[k - 1] / &norm;
+        self.norms[k] = &self.norms[k - 1] * &self.norms[k] / &norm;
+        self.norms[k - 1] = norm;
+
+        for i in k + 1..=kmax {
+            let t = self.mu[i][k].clone();
+            self.mu[i][k] = &self.mu[i][k - 1] - &m * &t;
+            self.mu[i][k - 1] = t + &self.mu[k][k - 1] * &self.mu[i][k];
+        }
+    }
+}
+
+// Reduce a basis of linearly independent vectors. delta = 3/4 is the
+// classic choice, closer to 1 gives shorter vectors but takes longer.
+pub fn lll(basis: &[Vector], delta: &BigRational) -> Vec<Vector> {
+    let n = basis.len();
+    if n < 2 {
+        return basis.to_vec();
+    }
+    let mut state = Reduction {
+        basis: basis.to_vec(),
+        mu: vec![vec![BigRational::zero(); n]; n],
+        norms: vec![BigRational::zero(); n],
+    };
+    state.orthogonalise(0);
+    let mut k = 1;
+    // How far Gram-Schmidt has got
+    let mut kmax = 0;
+    while k < n {
+        if k > kmax {
+            kmax = k;
+            state.orthogonalise(k);
+        }
+        state.reduce(k, k - 1);
+        let mu = &state.mu[k][k - 1];
+        if state.norms[k] < (delta - mu * mu) * &state.norms[k - 1] {
+            state.swap(k, kmax);
+            k = (k - 1).max(1);
+            continue;
+        }
+        for l in (0..k - 1).rev() {
+            state.reduce(k, l);
+        }
+        k += 1;
+    }
+    state.basis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn vector(entries: &[(i64, i64)]) -> Vector {
+        entries.iter().map(|&(n, d)| ratio(n, d)).collect()
+    }
+
+    fn integers(entries: &[i64]) -> Vector {
+        entries.iter().map(|&n| ratio(n, 1)).collect()
+    }
+
+    // Cofactor expansion, fine for matrices this small
+    fn determinant(rows: &[Vector]) -> BigRational {
+        if rows.len() == 1 {
+            return rows[0][0].clone();
+        }
+        (0..rows.len()).fold(BigRational::zero(), |acc, j| {
+            let minor = rows[1..].iter()
+                .map(|row| row.iter().enumerate().filter(|&(c, _)| c != j).map(|(_, x)| x.clone()).collect())
+                .collect::<Vec<Vector>>();
+            let term = &rows[0][j] * determinant(&minor);
+            if j % 2 == 0 { acc + term } else { acc - term }
+        })
+    }
+
+    #[test]
+    fn gram_schmidt_is_orthogonal() {
+        let basis = vec![integers(&[3, 1, 0]), integers(&[2, 2, 1]), integers(&[1, 0, 5])];
+        let (orthogonal, mu) = gram_schmidt(&basis);
+        for i in 0..3 {
+            for j in 0..i {
+                assert!(dot(&orthogonal[i], &orthogonal[j]).is_zero());
+            }
+        }
+        assert_eq!(mu[1][0], ratio(8, 10));
+    }
+
+    // The example from the Wikipedia article
+    #[test]
+    fn reduces_textbook_basis() {
+        let basis = vec![integers(&[1, 1, 1]), integers(&[-1, 0, 2]), integers(&[3, 5, 6])];
+        let reduced = lll(&basis, &ratio(3, 4));
+        assert_eq!(reduced, vec![integers(&[0, 1, 0]), integers(&[1, 0, 1]), integers(&[-1, 0, 2])]);
+        assert!(is_reduced(&reduced, &ratio(3, 4)));
+    }
+
+    // And the one from challenge 62, which has fractions in it
+    #[test]
+    fn challenge62_reduces_rational_basis() {
+        let basis = vec![
+            vector(&[(-2, 1), (0, 1), (2, 1), (0, 1)]),
+            vector(&[(1, 2), (-1, 1), (0, 1), (0, 1)]),
+            vector(&[(-1, 1), (0, 1), (-2, 1), (1, 2)]),
+            vector(&[(-1, 1), (1, 1), (1, 1), (2, 1)]),
+        ];
+        let reduced = lll(&basis, &ratio(99, 100));
+        assert_eq!(reduced, vec![
+            vector(&[(1, 2), (-1, 1), (0, 1), (0, 1)]),
+            vector(&[(-1, 1), (0, 1), (-2, 1), (1, 2)]),
+            vector(&[(-1, 2), (0, 1), (1, 1), (2, 1)]),
+            vector(&[(-3, 2), (-1, 1), (2, 1), (0, 1)]),
+        ]);
+    }
+
+    // Random bases are reduced afterwards and still span the same lattice
+    #[test]
+    fn random_bases_stay_the_same_lattice() {
+        let mut rng = StdRng::seed_from_u64(62);
+        let delta = ratio(99, 100);
+        for _ in 0..8 {
+            let basis = (0..5)
+                .map(|_| (0..5).map(|_| rng.gen_range(-1000, 1000)).collect::<Vec<_>>())
+                .map(|row| integers(&row))
+                .collect::<Vec<_>>();
+            let before = determinant(&basis).abs();
+            if before.is_zero() {
+                continue;
+            }
+            let reduced = lll(&basis, &delta);
+            assert!(is_reduced(&reduced, &delta));
+            assert_eq!(determinant(&reduced).abs(), before);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::rsa::int_to_bytes;
+
+use crate::ec::{sqrt_mod, Curve, Point};
+
+/*
+ * Montgomery curves
+ *
+ * Bv^2 = u^3 + Au^2 + u. The ladder gets the u coordinate of kP from the u
+ * coordinate of P and nothing else: it walks R0 = nP and R1 = (n + 1)P up the
+ * bits of k, and their difference is always P, which is all differential
+ * addition needs. Projective (X : Z) coordinates keep the inversions out of
+ * the loop, and infinity comes out as u = 0, the same as the point of order 2.
+ *
+ * Every Montgomery curve is a Weierstrass curve in disguise:
+ *   x = u/B + A/3B,  y = v/B
+ *
+ * The ladder never looks at B, so it's just as happy with a u that isn't on
+ * the curve at all. Those are on the quadratic twist, dv^2 = u^3 + Au^2 + u
+ * for a non-square d, which has a different order of its own.
+ */
+
+// (X : Z) stands for u = X/Z
+pub type Projective = (BigUint, BigUint);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MontgomeryCurve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+}
+
+impl MontgomeryCurve {
+    pub fn new(p: BigUint, a: BigUint, b: BigUint) -> Self {
+        MontgomeryCurve { p, a, b }
+    }
+
+    // v^2 = u^3 + 534u^2 + u, the challenge 59 curve with u = x - 178
+    pub fn challenge60() -> Self {
+        MontgomeryCurve::new(Curve::challenge59().p, BigUint::from(534u32), BigUint::one())
+    }
+
+    // Fermat, as in Curve::inv, except here 0 going to 0 is on purpose:
+    // to_affine counts on it to send infinity to u = 0
+    fn inv(&self, a: &BigUint) -> BigUint {
+        a.modpow(&(&self.p - 2u32), &self.p)
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.p - b) % &self.p
+    }
+
+    // (u^3 + Au^2 + u) / B
+    pub fn rhs(&self, u: &BigUint) -> BigUint {
+        let p = &self.p;
+        let u2 = u * u % p;
+        (&u2 * u + &self.a * u2 + u) % p * self.inv(&self.b) % p
+    }
+
+    // Otherwise it's on the twist
+    pub fn on_curve(&self, u: &BigUint) -> bool {
+        sqrt_mod(&self.rhs(u), &self.p).is_some()
+    }
+
+    // a = (3 - A^2) / 3B^2,  b = (2A^3 - 9A) / 27B^3
+    pub fn to_weierstrass(&self) -> Curve {
+        let p = &self.p;
+        let (a, b) = (&self.a, &self.b);
+        let a2 = a * a % p;
+        let wa = self.sub(&BigUint::from(3u32), &a2) * self.inv(&(b * b * 3u32 % p)) % p;
+        let wb = self.sub(&(&a2 * a * 2u32 % p), &(a * 9u32 % p)) * self.inv(&(b * b * b * 27u32 % p)) % p;
+        Curve::new(p.clone(), wa, wb)
+    }
+
+    pub fn to_weierstrass_point(&self, u: &BigUint, v: &BigUint) -> Point {
+        let p = &self.p;
+        let b_inv = self.inv(&self.b);
+        let shift = &self.a * self.inv(&(&self.b * 3u32 % p)) % p;
+        Point::Affine((u * &b_inv + shift) % p, v * b_inv % p)
+    }
+
+    pub fn from_weierstrass_point(&self, point: &Point) -> Option<(BigUint, BigUint)> {
+        let (x, y) = match point {
+            Point::Infinity => return None,
+            Point::Affine(x, y) => (x, y),
+        };
+        let p = &self.p;
+        let shift = &self.a * self.inv(&(&self.b * 3u32 % p)) % p;
+        Some((self.sub(x, &shift) * &self.b % p, y * &self.b % p))
+    }
+
+    // One of the two Weierstrass points with this u, if it's on the curve
+    pub fn lift(&self, u: &BigUint) -> Option<Point> {
+        let v = sqrt_mod(&self.rhs(u), &self.p)?;
+        Some(self.to_weierstrass_point(u, &v))
+    }
+
+    pub fn random_twist_point<R: Rng>(&self, rng: &mut R) -> BigUint {
+        loop {
+            let u = rng.gen_biguint_below(&self.p);
+            if !self.on_curve(&u) {
+                return u;
+            }
+        }
+    }
+
+    pub fn xdbl(&self, point: &Projective) -> Projective {
+        let p = &self.p;
+        let (x, z) = point;
+        let sum = (x + z) * (x + z) % p;
+        let diff = self.sub(x, z);
+        let diff = &diff * &diff % p;
+        // 4XZ, and (A + 2)/4 times it
+        let cross = self.sub(&sum, &diff);
+        let a24 = (&self.a + 2u32) * self.inv(&BigUint::from(4u32)) % p;
+        let x2 = &sum * &diff % p;
+        let z2 = (diff + a24 * &cross) % p * cross % p;
+        (x2, z2)
+    }
+
+    // P + Q given P - Q
+    pub fn xadd(&self, point: &Projective, other: &Projective, difference: &Projective) -> Projective {
+        let p = &self.p;
+        let (x2, z2) = point;
+        let (x3, z3) = other;
+        let (x1, z1) = difference;
+        let da = self.sub(x2, z2) * (x3 + z3) % p;
+        let cb = (x2 + z2) * self.sub(x3, z3) % p;
+        let sum = (&da + &cb) % p;
+        let diff = self.sub(&da, &cb);
+        (z1 * (&sum * &sum % p) % p, x1 * (&diff * &diff % p) % p)
+    }
+
+    // u, or 0 for infinity
+    pub fn to_affine(&self, point: &Projective) -> BigUint {
+        &point.0 * self.inv(&point.1) % &self.p
+    }
+
+    // Every point at the cost of a single inversion (Montgomery's trick), for
+    // points that aren't infinity
+    pub fn batch_to_affine(&self, points: &[Projective]) -> Vec<BigUint> {
+        let p = &self.p;
+        let mut prefix = Vec::with_capacity(points.len());
+        let mut product = BigUint::one();
+        for (_, z) in points {
+            prefix.push(product.clone());
+            product = product * z % p;
+        }
+        // product^-1 = (z_0 ... z_i)^-1, peel a z off each time round
+        let mut inverse = self.inv(&product);
+        let mut us = vec![BigUint::zero(); points.len()];
+        for (i, (x, z)) in points.iter().enumerate().rev() {
+            us[i] = x * (&inverse * &prefix[i] % p) % p;
+            inverse = inverse * z % p;
+        }
+        us
+    }
+
+    pub fn ladder(&self, u: &BigUint, k: &BigUint) -> BigUint {
+        let base = (u.clone(), BigUint::one());
+        let mut r0 = (BigUint::one(), BigUint::zero());
+        let mut r1 = base.clone();
+        for i in (0..self.p.bits()).rev() {
+            if k.bit(i) {
+                r0 = self.xadd(&r0, &r1, &base);
+                r1 = self.xdbl(&r1);
+            } else {
+                r1 = self.xadd(&r0, &r1, &base);
+                r0 = self.xdbl(&r0);
+            }
+        }
+        self.to_affine(&r0)
+    }
+
+    // Big-endian u, padded to the size of p
+    pub fn encode(&self, u: &BigUint) -> Vec<u8> {
+        int_to_bytes(u, (self.p.bits() as usize).div_ceil(8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ec::Params;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn challenge60_same_curve() {
+        let curve = MontgomeryCurve::challenge60();
+        assert_eq!(curve.to_weierstrass(), Curve::challenge59());
+        let g = Params::challenge59().g;
+        let (u, v) = curve.from_weierstrass_point(&g).unwrap();
+        assert_eq!(u, BigUint::from(4u32));
+        assert_eq!(curve.to_weierstrass_point(&u, &v), g);
+        assert!(curve.on_curve(&u));
+    }
+
+    #[test]
+    fn challenge60_ladder_matches_weierstrass() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let params = Params::challenge59();
+        for _ in 0..4 {
+            let k = rng.gen_biguint_below(&params.n);
+            let (u, _) = curve.from_weierstrass_point(&params.curve.mul(&params.g, &k)).unwrap();
+            assert_eq!(curve.ladder(&BigUint::from(4u32), &k), u);
+        }
+        assert!(curve.ladder(&BigUint::from(4u32), &params.n).is_zero());
+    }
+
+    #[test]
+    fn differential_addition_walks_multiples() {
+        let curve = MontgomeryCurve::challenge60();
+        let base = (BigUint::from(4u32), BigUint::one());
+        let mut prev = base.clone();
+        let mut cur = curve.xdbl(&base);
+        let mut points = vec![prev.clone(), cur.clone()];
+        for _ in 0..20 {
+            let next = curve.xadd(&cur, &base, &prev);
+            prev = std::mem::replace(&mut cur, next);
+            points.push(cur.clone());
+        }
+        let us = curve.batch_to_affine(&points);
+        for (k, u) in us.iter().enumerate() {
+            assert_eq!(&curve.ladder(&BigUint::from(4u32), &BigUint::from(k + 1)), u);
+        }
+    }
+
+    #[test]
+    fn lift_round_trips() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let weierstrass = curve.to_weierstrass();
+        let point = weierstrass.random_point(&mut rng);
+        let (u, _) = curve.from_weierstrass_point(&point).unwrap();
+        let lifted = curve.lift(&u).unwrap();
+        assert!(lifted == point || lifted == weierstrass.negate(&point));
+        assert!(curve.lift(&curve.random_twist_point(&mut rng)).is_none());
+    }
+}
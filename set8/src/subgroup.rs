@@ -0,0 +1,209 @@
+use std::cell::Cell;
+
+use hmac::{Hmac, Mac, NewMac};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::dh::KeyPair;
+use set5::numtheory::{crt, primes_below};
+use sha2::Sha256;
+
+pub const MESSAGE: &[u8] = b"crazy flamboyant for the rap enjoyment";
+pub const SMALL_FACTOR_LIMIT: u32 = 1 << 16;
+
+/*
+ * Small subgroup confinement
+ *
+ * g generates a subgroup of prime order q, but p - 1 = qj and j has plenty of
+ * small factors. For each small prime r dividing j, h = rand^((p-1)/r) has
+ * order r. Bob doesn't check that what we send him is in g's subgroup, so he
+ * answers with a MAC under K = h^x, which can only be one of r values:
+ * try them all and we know x mod r. Once the product of the r passes q, CRT
+ * hands back x itself.
+ */
+
+const CHALLENGE57_P: &str = "\
+    7199773997391911030609999317773941274322764333428698921736339643928346453700085358\
+    802973900485592910475480089726140708102474957429903531369589969318716771";
+const CHALLENGE57_G: &str = "\
+    4565356397095740655436854503483826832136106141639563487732438195343690437606117828\
+    318042418238184896212352329118608100083187535033402010599512641674644143";
+const CHALLENGE57_Q: &str = "236234353446506858198510045061214171961";
+const CHALLENGE58_P: &str = "\
+    1147037487492527565811666350723216140208665025845389627453499167689899926264158151\
+    9101074740642369848233294239851519212341844337347119899874391456329785623";
+const CHALLENGE58_G: &str = "\
+    6229523353339612969781592660847410858898813587384599399782901799360636355667402585\
+    55167783009058567397963466103140082647486611657350811560630587013183357";
+const CHALLENGE58_Q: &str = "335062023296420808191071248367701059461";
+
+fn dec(s: &str) -> BigUint {
+    BigUint::parse_bytes(s.as_bytes(), 10).unwrap()
+}
+
+// A group where g generates a subgroup of prime order q
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+impl Params {
+    pub fn new(p: BigUint, q: BigUint, g: BigUint) -> Self {
+        Params { p, q, g }
+    }
+
+    pub fn challenge57() -> Self {
+        Params::new(dec(CHALLENGE57_P), dec(CHALLENGE57_Q), dec(CHALLENGE57_G))
+    }
+
+    // Same size as challenge 57, but j's small factors come to about 2^88
+    pub fn challenge58() -> Self {
+        Params::new(dec(CHALLENGE58_P), dec(CHALLENGE58_Q), dec(CHALLENGE58_G))
+    }
+
+    // j = (p - 1) / q
+    pub fn cofactor(&self) -> BigUint {
+        (&self.p - 1u32) / &self.q
+    }
+
+    pub fn generate_keypair<R: Rng>(&self, rng: &mut R) -> KeyPair {
+        let private = rng.gen_biguint_range(&BigUint::one(), &self.q);
+        let public = self.g.modpow(&private, &self.p);
+        KeyPair { private, public }
+    }
+}
+
+// HMAC-SHA256 keyed with the shared secret's bytes
+pub fn mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Bob takes whatever public key he's sent and proves he has the shared secret
+pub struct Bob {
+    params: Params,
+    keys: KeyPair,
+    queries: Cell<usize>,
+}
+
+impl Bob {
+    pub fn new<R: Rng>(params: Params, rng: &mut R) -> Self {
+        let keys = params.generate_keypair(rng);
+        Bob { params, keys, queries: Cell::new(0) }
+    }
+
+    pub fn public(&self) -> &BigUint {
+        &self.keys.public
+    }
+
+    // (message, MAC(K, message)) with K = h^x
+    pub fn respond(&self, h: &BigUint) -> (Vec<u8>, Vec<u8>) {
+        self.queries.set(self.queries.get() + 1);
+        let key = h.modpow(&self.keys.private, &self.params.p);
+        (MESSAGE.to_vec(), mac(&key.to_bytes_be(), MESSAGE))
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+// The distinct primes below `limit` that divide n
+pub fn small_factors(n: &BigUint, limit: u32) -> Vec<u32> {
+    primes_below(limit).into_iter().filter(|&r| (n % r).is_zero()).collect()
+}
+
+// Some h != 1 with h^r = 1, for r a prime dividing p - 1
+pub fn element_of_order<R: Rng>(p: &BigUint, r: u32, rng: &mut R) -> BigUint {
+    let exponent = (p - 1u32) / r;
+    loop {
+        let h = rng.gen_biguint_range(&BigUint::from(2u32), p).modpow(&exponent, p);
+        if !h.is_one() {
+            return h;
+        }
+    }
+}
+
+// The k in [0, r) with MAC(h^k, message) = tag
+fn brute_force(p: &BigUint, h: &BigUint, r: u32, message: &[u8], tag: &[u8]) -> Option<u32> {
+    let mut key = BigUint::one();
+    for k in 0..r {
+        if mac(&key.to_bytes_be(), message) == tag {
+            return Some(k);
+        }
+        key = key * h % p;
+    }
+    None
+}
+
+// Bob's private key mod small factors of j until their product passes q or we
+// run out, as (x mod m, m)
+pub fn leak_residues<R: Rng>(bob: &Bob, params: &Params, limit: u32, rng: &mut R) -> Option<(BigUint, BigUint)> {
+    let mut congruences = vec![];
+    let mut modulus = BigUint::one();
+    for r in small_factors(&params.cofactor(), limit) {
+        if modulus > params.q {
+            break;
+        }
+        let h = element_of_order(&params.p, r, rng);
+        let (message, tag) = bob.respond(&h);
+        let residue = brute_force(&params.p, &h, r, &message, &tag)?;
+        congruences.push((BigUint::from(residue), BigUint::from(r)));
+        modulus *= r;
+    }
+    crt(&congruences)
+}
+
+pub fn recover_private<R: Rng>(bob: &Bob, params: &Params, rng: &mut R) -> Option<BigUint> {
+    let (x, modulus) = leak_residues(bob, params, SMALL_FACTOR_LIMIT, rng)?;
+    if modulus > params.q {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use set5::numtheory::{is_probable_prime, MR_ROUNDS};
+
+    #[test]
+    fn small_factors_are_distinct() {
+        assert_eq!(small_factors(&BigUint::from(2u32 * 2 * 3 * 7 * 7 * 101), 100), vec![2, 3, 7]);
+    }
+
+    #[test]
+    fn challenge57_and_58_params() {
+        let mut rng = StdRng::seed_from_u64(57);
+        for params in &[Params::challenge57(), Params::challenge58()] {
+            assert!(is_probable_prime(&params.q, MR_ROUNDS, &mut rng));
+            assert_eq!(&params.cofactor() * &params.q + 1u32, params.p);
+            assert!(params.g.modpow(&params.q, &params.p).is_one());
+        }
+    }
+
+    #[test]
+    fn challenge57_element_of_order() {
+        let mut rng = StdRng::seed_from_u64(57);
+        let params = Params::challenge57();
+        for r in small_factors(&params.cofactor(), 1 << 8) {
+            let h = element_of_order(&params.p, r, &mut rng);
+            assert!(h.modpow(&BigUint::from(r), &params.p).is_one());
+        }
+    }
+
+    #[test]
+    fn challenge57_recover_private() {
+        let mut rng = StdRng::seed_from_u64(57);
+        let params = Params::challenge57();
+        let bob = Bob::new(params.clone(), &mut rng);
+        assert_eq!(recover_private(&bob, &params, &mut rng), Some(bob.keys.private.clone()));
+        assert!(bob.queries() < 16);
+    }
+}
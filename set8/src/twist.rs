@@ -0,0 +1,247 @@
+use std::cell::Cell;
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use set5::numtheory::crt;
+
+use crate::ec::Params;
+use crate::kangaroo::{kangaroo, Jumps};
+use crate::montgomery::{MontgomeryCurve, Projective};
+use crate::subgroup::{mac, small_factors, MESSAGE};
+
+/*
+ * Twist attack on x-only ECDH
+ *
+ * Bob only ever sees u coordinates, so there's no b to tamper with. But every
+ * u that isn't on his curve is on its twist, and the ladder will happily
+ * multiply it. The twist's order has small factors where the curve's doesn't:
+ * points of small order r there leak x mod r, as in challenge 59.
+ *
+ * Only up to sign, though: kP and -kP share a u coordinate, so all we learn
+ * is x = +-k mod r. A point of order r0 r settles which sign goes with which
+ * between two factors, which leaves x = +-n mod R for the product R of them
+ * all. That's x = +-n + mR, and with Bob's public u lifted to one of +-xG,
+ *   +-xG -+ nG = mR G
+ * for |m| below the order over R: a couple of kangaroos over [-W, W] find it.
+ *
+ * For a full-size key that's every twist factor up to 2323367 and a kangaroo
+ * over a ~2^40 range, a few minutes even in release, so it only runs in the
+ * ignored challenge60_twist_attack test. The default tests cover the leak and
+ * the sign alignment on the real curve, then run the whole attack end to end
+ * against a 64-bit key.
+ */
+
+pub const CHALLENGE60_TWIST_ORDER: &str = "233970423115425145549737651362517029924";
+// Big enough for 2323367, which the kangaroo can't do without
+pub const TWIST_FACTOR_LIMIT: u32 = 1 << 22;
+const BATCH_SIZE: usize = 1024;
+
+pub fn twist_order() -> BigUint {
+    BigUint::parse_bytes(CHALLENGE60_TWIST_ORDER.as_bytes(), 10).unwrap()
+}
+
+// Bob publishes u(xG) and answers with MACs keyed by u(xh) for whatever u we send
+pub struct Bob {
+    curve: MontgomeryCurve,
+    private: BigUint,
+    public: BigUint,
+    queries: Cell<usize>,
+}
+
+impl Bob {
+    pub fn new<R: Rng>(curve: MontgomeryCurve, g: &BigUint, n: &BigUint, rng: &mut R) -> Self {
+        let private = rng.gen_biguint_range(&BigUint::one(), n);
+        let public = curve.ladder(g, &private);
+        Bob { curve, private, public, queries: Cell::new(0) }
+    }
+
+    pub fn public(&self) -> &BigUint {
+        &self.public
+    }
+
+    pub fn respond(&self, u: &BigUint) -> (Vec<u8>, Vec<u8>) {
+        self.queries.set(self.queries.get() + 1);
+        let key = self.curve.ladder(u, &self.private);
+        (MESSAGE.to_vec(), mac(&self.curve.encode(&key), MESSAGE))
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+}
+
+// The odd primes below `limit` dividing the twist order. 2 is no use: the
+// ladder sends infinity and the point of order 2 to the same u = 0.
+pub fn twist_factors(twist_order: &BigUint, limit: u32) -> Vec<u32> {
+    small_factors(twist_order, limit).into_iter().filter(|&r| r != 2).collect()
+}
+
+// A point on the twist of order exactly the product of `factors`, each of which
+// has to divide the twist order only once
+pub fn twist_point_of_order<R: Rng>(curve: &MontgomeryCurve, twist_order: &BigUint, factors: &[u32], rng: &mut R) -> BigUint {
+    let order = factors.iter().fold(BigUint::one(), |acc, &r| acc * r);
+    let cofactor = twist_order / &order;
+    loop {
+        let h = curve.ladder(&curve.random_twist_point(rng), &cofactor);
+        if factors.iter().all(|&r| !curve.ladder(&h, &(&order / r)).is_zero()) {
+            return h;
+        }
+    }
+}
+
+// The k in [0, r/2] with MAC(u(kh), message) = tag. Walks kh by differential
+// addition, a batch at a time so each batch only needs the one inversion.
+fn brute_force(curve: &MontgomeryCurve, h: &BigUint, r: u32, message: &[u8], tag: &[u8]) -> Option<u32> {
+    let matches = |u: &BigUint| mac(&curve.encode(u), message) == tag;
+    if matches(&BigUint::zero()) {
+        return Some(0);
+    }
+    let base = (h.clone(), BigUint::one());
+    let mut prev: Projective = (BigUint::one(), BigUint::zero());
+    let mut cur = base.clone();
+    let mut k = 1;
+    while k <= r / 2 {
+        let mut batch = vec![];
+        while batch.len() < BATCH_SIZE && k + batch.len() as u32 <= r / 2 {
+            let next = if prev.1.is_zero() { curve.xdbl(&cur) } else { curve.xadd(&cur, &base, &prev) };
+            prev = std::mem::replace(&mut cur, next);
+            batch.push(prev.clone());
+        }
+        if let Some(i) = curve.batch_to_affine(&batch).iter().position(matches) {
+            return Some(k + i as u32);
+        }
+        k += batch.len() as u32;
+    }
+    None
+}
+
+// (r, k) with x = +-k mod r for every factor
+pub fn leak_residues<R: Rng>(bob: &Bob, curve: &MontgomeryCurve, twist_order: &BigUint, factors: &[u32], rng: &mut R) -> Option<Vec<(u32, u32)>> {
+    factors.iter()
+        .map(|&r| {
+            let h = twist_point_of_order(curve, twist_order, &[r], rng);
+            let (message, tag) = bob.respond(&h);
+            Some((r, brute_force(curve, &h, r, &message, &tag)?))
+        })
+        .collect()
+}
+
+// Flips residues so they all share the sign of the first nonzero one
+pub fn align_signs<R: Rng>(bob: &Bob, curve: &MontgomeryCurve, twist_order: &BigUint, residues: &[(u32, u32)], rng: &mut R) -> Option<Vec<(u32, u32)>> {
+    let &(r0, k0) = match residues.iter().find(|&&(_, k)| k != 0) {
+        Some(reference) => reference,
+        None => return Some(residues.to_vec()),
+    };
+    residues.iter()
+        .map(|&(r, k)| {
+            if r == r0 || k == 0 {
+                return Some((r, k));
+            }
+            let h = twist_point_of_order(curve, twist_order, &[r0, r], rng);
+            let (message, tag) = bob.respond(&h);
+            let (same, _) = crt(&[(BigUint::from(k0), BigUint::from(r0)), (BigUint::from(k), BigUint::from(r))])?;
+            if mac(&curve.encode(&curve.ladder(&h, &same)), &message) == tag {
+                Some((r, k))
+            } else {
+                Some((r, r - k))
+            }
+        })
+        .collect()
+}
+
+// Bob's private key, known to be below `bound` (the order of the base point if
+// there's nothing better to go on), using the twist's factors below `limit`
+pub fn recover_private<R: Rng>(bob: &Bob, curve: &MontgomeryCurve, params: &Params, bound: &BigUint, limit: u32, rng: &mut R) -> Option<BigUint> {
+    let twist_order = twist_order();
+    let factors = twist_factors(&twist_order, limit);
+    let residues = leak_residues(bob, curve, &twist_order, &factors, rng)?;
+    let residues = align_signs(bob, curve, &twist_order, &residues, rng)?;
+    let congruences = residues.iter().map(|&(r, k)| (BigUint::from(k), BigUint::from(r))).collect::<Vec<_>>();
+    let (n, modulus) = crt(&congruences)?;
+
+    let weierstrass = &params.curve;
+    let order = &params.n;
+    let lifted = curve.lift(bob.public())?;
+    let step = weierstrass.mul(&params.g, &modulus);
+    let width = bound / &modulus + 1u32;
+    let jumps = Jumps::for_width(&(&width * 2u32));
+    for offset in &[n.clone(), &modulus - &n] {
+        // lifted + (W R - offset) G = (m + W) R G
+        let shift = (&width * &modulus % order + order - offset % order) % order;
+        let target = weierstrass.add(&lifted, &weierstrass.mul(&params.g, &shift));
+        let m = match kangaroo(weierstrass, &step, &target, &BigUint::zero(), &(&width * 2u32), &jumps) {
+            Some(m) => m,
+            None => continue,
+        };
+        // lifted is one of +-xG, and only x itself is +-n mod R
+        let candidate = (m * &modulus % order + order - shift) % order;
+        for x in &[candidate.clone(), (order - &candidate) % order] {
+            let residue = x % &modulus;
+            if residue == n || residue == (&modulus - &n) % &modulus {
+                return Some(x.clone());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn challenge60_twist_order() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let u = curve.random_twist_point(&mut rng);
+        assert!(curve.ladder(&u, &twist_order()).is_zero());
+        assert_eq!(twist_factors(&twist_order(), TWIST_FACTOR_LIMIT), vec![11, 107, 197, 1621, 105143, 405373, 2323367]);
+    }
+
+    #[test]
+    fn challenge60_leak_up_to_sign() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let params = Params::challenge59();
+        let bob = Bob::new(curve.clone(), &BigUint::from(4u32), &params.n, &mut rng);
+        let factors = twist_factors(&twist_order(), 1 << 12);
+        let residues = leak_residues(&bob, &curve, &twist_order(), &factors, &mut rng).unwrap();
+        for &(r, k) in &residues {
+            let x = &bob.private % r;
+            assert!(x == BigUint::from(k) || x == BigUint::from(r - k));
+        }
+        let aligned = align_signs(&bob, &curve, &twist_order(), &residues, &mut rng).unwrap();
+        let signs = aligned.iter()
+            .map(|&(r, k)| (&bob.private % r) == BigUint::from(k))
+            .collect::<Vec<_>>();
+        assert!(signs.iter().all(|&same| same == signs[0]));
+    }
+
+    // Bob's key is cut down to 64 bits so the kangaroo finishes in a debug build
+    #[test]
+    fn challenge60_small_key() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let params = Params::challenge59();
+        let bound = BigUint::one() << 64;
+        let bob = Bob::new(curve.clone(), &BigUint::from(4u32), &bound, &mut rng);
+        let x = recover_private(&bob, &curve, &params, &bound, 1 << 17, &mut rng).unwrap();
+        assert_eq!(x, bob.private);
+    }
+
+    // A few minutes even in release:
+    // cargo test --release challenge60_twist_attack -- --ignored
+    #[test]
+    #[ignore]
+    fn challenge60_twist_attack() {
+        let mut rng = StdRng::seed_from_u64(60);
+        let curve = MontgomeryCurve::challenge60();
+        let params = Params::challenge59();
+        let bob = Bob::new(curve.clone(), &BigUint::from(4u32), &params.n, &mut rng);
+        let x = recover_private(&bob, &curve, &params, &params.n, TWIST_FACTOR_LIMIT, &mut rng).unwrap();
+        assert_eq!(x, bob.private);
+    }
+}